@@ -0,0 +1,260 @@
+//! Owned request bodies.
+//!
+//! A `Body` usually borrows what it sends, which ties a `RequestBuilder`
+//! to the lifetime of a local buffer, and a body read from a stream can
+//! only be sent once. An `OwnedBody` owns its contents, and when it knows
+//! how to start over it can be sent again, to follow a 307 or 308 redirect,
+//! or to retry a request.
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::Arc;
+
+type Factory = Fn() -> io::Result<Box<Read + Send>> + Send + Sync;
+
+/// A request body that owns its contents.
+///
+/// # Example
+///
+/// ```no_run
+/// # use hyper::Client;
+/// use std::fs::File;
+/// use hyper::client::OwnedBody;
+///
+/// let client = Client::new();
+/// let body = OwnedBody::file(File::open("upload.bin").unwrap()).unwrap();
+/// let res = client.post("http://example.domain/upload").body(body).send().unwrap();
+/// ```
+pub struct OwnedBody {
+    kind: Kind,
+}
+
+enum Kind {
+    Bytes(Arc<Vec<u8>>, usize),
+    File {
+        file: File,
+        start: u64,
+        size: Option<u64>,
+        rewind: bool,
+    },
+    Reader {
+        reader: Option<Box<Read + Send>>,
+        size: Option<u64>,
+        factory: Option<Arc<Factory>>,
+    },
+}
+
+impl OwnedBody {
+    /// A body of bytes, which can always be sent again.
+    pub fn bytes<B: Into<Vec<u8>>>(bytes: B) -> OwnedBody {
+        OwnedBody {
+            kind: Kind::Bytes(Arc::new(bytes.into()), 0)
+        }
+    }
+
+    /// A body read from a `File`, starting at its current position.
+    ///
+    /// The size is taken from the file's metadata, and the body is sent
+    /// again by seeking back to where it started.
+    pub fn file(mut file: File) -> io::Result<OwnedBody> {
+        let start = try!(file.seek(SeekFrom::Current(0)));
+        let meta = try!(file.metadata());
+        let size = if meta.is_file() {
+            Some(meta.len().saturating_sub(start))
+        } else {
+            None
+        };
+        Ok(OwnedBody {
+            kind: Kind::File {
+                file: file,
+                start: start,
+                size: size,
+                rewind: false,
+            }
+        })
+    }
+
+    /// A body read from any reader, which can only be sent once.
+    ///
+    /// Without a size, the body is sent with chunked encoding.
+    pub fn reader<R: Read + Send + 'static>(reader: R, size: Option<u64>) -> OwnedBody {
+        OwnedBody {
+            kind: Kind::Reader {
+                reader: Some(Box::new(reader)),
+                size: size,
+                factory: None,
+            }
+        }
+    }
+
+    /// A body read from readers opened by `factory`.
+    ///
+    /// The factory is called when the body is first read, and again each
+    /// time the body has to be sent again. Every reader it returns must
+    /// produce the same contents.
+    pub fn with_factory<F>(size: Option<u64>, factory: F) -> OwnedBody
+    where F: Fn() -> io::Result<Box<Read + Send>> + Send + Sync + 'static {
+        OwnedBody {
+            kind: Kind::Reader {
+                reader: None,
+                size: size,
+                factory: Some(Arc::new(factory)),
+            }
+        }
+    }
+
+    /// The size of this body, if it is known.
+    pub fn size(&self) -> Option<u64> {
+        match self.kind {
+            Kind::Bytes(ref bytes, _) => Some(bytes.len() as u64),
+            Kind::File { size, .. } => size,
+            Kind::Reader { size, .. } => size,
+        }
+    }
+
+    /// Whether this body can be sent again.
+    pub fn is_replayable(&self) -> bool {
+        match self.kind {
+            Kind::Reader { ref factory, .. } => factory.is_some(),
+            _ => true
+        }
+    }
+
+    /// A copy of this body that reads from the start, if it can be sent
+    /// again.
+    pub fn try_clone(&self) -> Option<OwnedBody> {
+        let kind = match self.kind {
+            Kind::Bytes(ref bytes, _) => Kind::Bytes(bytes.clone(), 0),
+            Kind::File { ref file, start, size, .. } => match file.try_clone() {
+                // the clone shares a cursor with the original, so it
+                // seeks back when it is first read
+                Ok(file) => Kind::File {
+                    file: file,
+                    start: start,
+                    size: size,
+                    rewind: true,
+                },
+                Err(e) => {
+                    debug!("could not clone body file: {:?}", e);
+                    return None;
+                }
+            },
+            Kind::Reader { size, factory: Some(ref factory), .. } => Kind::Reader {
+                reader: None,
+                size: size,
+                factory: Some(factory.clone()),
+            },
+            Kind::Reader { factory: None, .. } => return None,
+        };
+        Some(OwnedBody { kind: kind })
+    }
+}
+
+impl Read for OwnedBody {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.kind {
+            Kind::Bytes(ref bytes, ref mut pos) => {
+                let n = try!(Read::read(&mut &bytes[*pos..], buf));
+                *pos += n;
+                Ok(n)
+            },
+            Kind::File { ref mut file, start, ref mut rewind, .. } => {
+                if *rewind {
+                    try!(file.seek(SeekFrom::Start(start)));
+                    *rewind = false;
+                }
+                file.read(buf)
+            },
+            Kind::Reader { ref mut reader, ref factory, .. } => {
+                if reader.is_none() {
+                    *reader = match *factory {
+                        Some(ref factory) => Some(try!(factory())),
+                        None => return Ok(0)
+                    };
+                }
+                reader.as_mut().unwrap().read(buf)
+            }
+        }
+    }
+}
+
+impl From<Vec<u8>> for OwnedBody {
+    fn from(bytes: Vec<u8>) -> OwnedBody {
+        OwnedBody::bytes(bytes)
+    }
+}
+
+impl From<String> for OwnedBody {
+    fn from(s: String) -> OwnedBody {
+        OwnedBody::bytes(s)
+    }
+}
+
+impl From<&'static str> for OwnedBody {
+    fn from(s: &'static str) -> OwnedBody {
+        OwnedBody::bytes(s)
+    }
+}
+
+impl fmt::Debug for OwnedBody {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            Kind::Bytes(..) => "Bytes",
+            Kind::File { .. } => "File",
+            Kind::Reader { .. } => "Reader",
+        };
+        f.debug_struct("OwnedBody")
+            .field("kind", &kind)
+            .field("size", &self.size())
+            .field("replayable", &self.is_replayable())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use super::OwnedBody;
+
+    fn read_all(body: &mut OwnedBody) -> String {
+        let mut s = String::new();
+        body.read_to_string(&mut s).unwrap();
+        s
+    }
+
+    #[test]
+    fn test_bytes_replay() {
+        let mut body = OwnedBody::from("foo=bar");
+        assert_eq!(body.size(), Some(7));
+        let mut replay = body.try_clone().unwrap();
+        assert_eq!(read_all(&mut body), "foo=bar");
+        assert_eq!(read_all(&mut body), "");
+        assert_eq!(read_all(&mut replay), "foo=bar");
+    }
+
+    #[test]
+    fn test_reader_not_replayable() {
+        let body = OwnedBody::reader(io::Cursor::new(b"foo".to_vec()), None);
+        assert_eq!(body.size(), None);
+        assert!(!body.is_replayable());
+        assert!(body.try_clone().is_none());
+    }
+
+    #[test]
+    fn test_factory_replay() {
+        let opened = Arc::new(AtomicUsize::new(0));
+        let counter = opened.clone();
+        let mut body = OwnedBody::with_factory(Some(3), move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(Box::new(io::Cursor::new(b"foo".to_vec())) as Box<Read + Send>)
+        });
+        let mut replay = body.try_clone().unwrap();
+        // readers are only opened when read
+        assert_eq!(opened.load(Ordering::SeqCst), 0);
+        assert_eq!(read_all(&mut body), "foo");
+        assert_eq!(read_all(&mut replay), "foo");
+        assert_eq!(opened.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,666 @@
+//! Client Cookie storage
+//!
+//! A `CookieStore` can be saved and loaded in the Netscape `cookies.txt`
+//! format used by curl and wget, or as JSON. It also implements
+//! `Encodable` and `Decodable`.
+use std::ascii::AsciiExt;
+use std::cmp;
+use std::io::{self, BufRead, Read, Write};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+use cookie::Cookie as CookiePair;
+use cookie::CookieJar;
+use serialize::{Decodable, Decoder, Encodable, Encoder};
+use serialize::json;
+use time::{self, Duration, Timespec};
+
+use Url;
+
+/// A store of the cookies sent by servers, to be sent back on later requests.
+///
+/// Cookies are kept according to the rules of
+/// [RFC6265](http://tools.ietf.org/html/rfc6265#section-5.3): a cookie is only
+/// sent to hosts matching its `Domain`, for urls under its `Path`, over HTTPS
+/// if it is `Secure`, and until it expires. `HttpOnly` cookies are kept and
+/// sent like any other, since every request of a `Client` is an HTTP request.
+///
+/// A `CookieStore` can be cloned cheaply, and clones share the same cookies,
+/// so one store can be used by several `Client`s at once.
+#[derive(Clone, Debug, Default)]
+pub struct CookieStore {
+    cookies: Arc<Mutex<Vec<StoredCookie>>>,
+}
+
+#[derive(Clone, Debug)]
+struct StoredCookie {
+    cookie: CookiePair,
+    domain: String,
+    path: String,
+    host_only: bool,
+    expires: Option<Timespec>,
+}
+
+impl StoredCookie {
+    fn is_expired(&self, now: Timespec) -> bool {
+        self.expires.map_or(false, |expires| expires <= now)
+    }
+
+    fn matches(&self, host: &str, path: &str, secure: bool) -> bool {
+        (secure || !self.cookie.secure) &&
+            if self.host_only {
+                host == self.domain
+            } else {
+                domain_matches(host, &self.domain)
+            } &&
+            path_matches(path, &self.path)
+    }
+}
+
+impl CookieStore {
+    /// Create an empty `CookieStore`.
+    pub fn new() -> CookieStore {
+        CookieStore::default()
+    }
+
+    /// Store the cookies a response from the url has set.
+    ///
+    /// Cookies that the url is not allowed to set, such as those for an
+    /// unrelated `Domain`, are ignored. A cookie that has already expired
+    /// removes the matching stored cookie.
+    pub fn store(&self, url: &Url, cookies: &[CookiePair]) {
+        let host = match request_host(url) {
+            Some(host) => host,
+            None => return
+        };
+        let now = time::get_time();
+        let mut stored = self.cookies.lock().unwrap();
+        for cookie in cookies {
+            let (domain, host_only) = match cookie.domain {
+                Some(ref domain) if !domain.is_empty() => {
+                    let domain = domain.trim_left_matches('.').to_ascii_lowercase();
+                    if !domain_matches(&host, &domain) {
+                        debug!("ignoring cookie {:?} for domain {:?} from {:?}",
+                               cookie.name, domain, host);
+                        continue;
+                    }
+                    if !domain.contains('.') {
+                        // a single label, such as a top level domain, would
+                        // send the cookie to every host under it
+                        if domain != host {
+                            debug!("ignoring cookie {:?} for top level domain {:?}",
+                                   cookie.name, domain);
+                            continue;
+                        }
+                        (domain, true)
+                    } else {
+                        (domain, false)
+                    }
+                },
+                _ => (host.clone(), true)
+            };
+            let path = match cookie.path {
+                Some(ref path) if path.starts_with('/') => path.clone(),
+                _ => default_path(url)
+            };
+            let expires = match (cookie.max_age, cookie.expires) {
+                (Some(max_age), _) => {
+                    Some(now + Duration::seconds(cmp::min(max_age, MAX_AGE) as i64))
+                },
+                (None, Some(ref tm)) => Some(tm.to_timespec()),
+                (None, None) => None
+            };
+
+            insert(&mut stored, StoredCookie {
+                cookie: cookie.clone(),
+                domain: domain,
+                path: path,
+                host_only: host_only,
+                expires: expires,
+            }, now);
+        }
+    }
+
+    /// The cookies that should be sent with a request to the url.
+    ///
+    /// Cookies with longer paths are listed first.
+    pub fn matching(&self, url: &Url) -> Vec<CookiePair> {
+        let host = match request_host(url) {
+            Some(host) => host,
+            None => return Vec::new()
+        };
+        let path = url.serialize_path().unwrap_or_else(|| "/".to_owned());
+        let secure = url.scheme == "https";
+        let now = time::get_time();
+
+        let mut stored = self.cookies.lock().unwrap();
+        stored.retain(|c| !c.is_expired(now));
+        let mut matching = stored.iter()
+            .filter(|c| c.matches(&host, &path, secure))
+            .collect::<Vec<_>>();
+        matching.sort_by(|a, b| b.path.len().cmp(&a.path.len()));
+        matching.into_iter()
+            .map(|c| CookiePair::new(c.cookie.name.clone(), c.cookie.value.clone()))
+            .collect()
+    }
+
+    /// All of the unexpired cookies in the store.
+    ///
+    /// The `domain` and `path` of each cookie are set to where it applies.
+    pub fn cookies(&self) -> Vec<CookiePair> {
+        let now = time::get_time();
+        let mut stored = self.cookies.lock().unwrap();
+        stored.retain(|c| !c.is_expired(now));
+        stored.iter().map(|c| {
+            let mut cookie = c.cookie.clone();
+            cookie.domain = Some(c.domain.clone());
+            cookie.path = Some(c.path.clone());
+            cookie
+        }).collect()
+    }
+
+    /// Remove all cookies from the store.
+    pub fn clear(&self) {
+        self.cookies.lock().unwrap().clear();
+    }
+
+    /// Add all of the unexpired cookies in the store to a `CookieJar`.
+    ///
+    /// A `CookieJar` keys cookies by name alone, so of several cookies with
+    /// the same name, only the last is kept.
+    /// Note that this will `panic!` if `CookieJar` is not root.
+    pub fn apply_to_cookie_jar(&self, jar: &mut CookieJar) {
+        for cookie in self.cookies() {
+            jar.add_original(cookie);
+        }
+    }
+
+    /// Load cookies in the Netscape `cookies.txt` format.
+    ///
+    /// Loaded cookies replace stored cookies with the same name, domain
+    /// and path. Cookies that have already expired are skipped.
+    pub fn load_netscape<R: BufRead>(&self, input: R) -> ::Result<()> {
+        let now = time::get_time();
+        let mut stored = self.cookies.lock().unwrap();
+        for (i, line) in input.lines().enumerate() {
+            let line = try!(line);
+            let line = line.trim_right_matches('\r');
+            let (line, httponly) = if line.starts_with(HTTP_ONLY_PREFIX) {
+                (&line[HTTP_ONLY_PREFIX.len()..], true)
+            } else {
+                (line, false)
+            };
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match parse_netscape_line(line, httponly) {
+                Some(cookie) => insert(&mut stored, cookie, now),
+                None => {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData,
+                        format!("invalid cookies.txt line {}", i + 1)).into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Save the unexpired cookies in the Netscape `cookies.txt` format.
+    ///
+    /// Session cookies are written with an expiry of `0`.
+    pub fn save_netscape<W: Write>(&self, mut output: W) -> io::Result<()> {
+        fn flag(val: bool) -> &'static str {
+            if val { "TRUE" } else { "FALSE" }
+        }
+
+        let now = time::get_time();
+        let mut stored = self.cookies.lock().unwrap();
+        stored.retain(|c| !c.is_expired(now));
+        try!(output.write_all(b"# Netscape HTTP Cookie File\n"));
+        for c in stored.iter() {
+            try!(write!(output, "{}{}{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                        if c.cookie.httponly { HTTP_ONLY_PREFIX } else { "" },
+                        if c.host_only { "" } else { "." },
+                        c.domain,
+                        flag(!c.host_only),
+                        c.path,
+                        flag(c.cookie.secure),
+                        c.expires.map_or(0, |t| t.sec),
+                        c.cookie.name,
+                        c.cookie.value));
+        }
+        output.flush()
+    }
+
+    /// Load cookies saved as JSON by `save_json`.
+    ///
+    /// Loaded cookies replace stored cookies with the same name, domain
+    /// and path. Cookies that have already expired are skipped.
+    pub fn load_json<R: Read>(&self, mut input: R) -> ::Result<()> {
+        let mut text = String::new();
+        try!(input.read_to_string(&mut text));
+        let cookies: Vec<StoredCookie> = try!(json::decode(&text));
+        let now = time::get_time();
+        let mut stored = self.cookies.lock().unwrap();
+        for cookie in cookies {
+            insert(&mut stored, cookie, now);
+        }
+        Ok(())
+    }
+
+    /// Save the unexpired cookies as JSON.
+    pub fn save_json<W: Write>(&self, mut output: W) -> ::Result<()> {
+        let text = try!(json::encode(self));
+        try!(output.write_all(text.as_bytes()));
+        try!(output.flush());
+        Ok(())
+    }
+}
+
+impl Encodable for CookieStore {
+    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        let now = time::get_time();
+        let mut stored = self.cookies.lock().unwrap();
+        stored.retain(|c| !c.is_expired(now));
+        stored.encode(s)
+    }
+}
+
+impl Decodable for CookieStore {
+    fn decode<D: Decoder>(d: &mut D) -> Result<CookieStore, D::Error> {
+        let mut cookies: Vec<StoredCookie> = try!(Decodable::decode(d));
+        let now = time::get_time();
+        cookies.retain(|c| !c.is_expired(now));
+        Ok(CookieStore {
+            cookies: Arc::new(Mutex::new(cookies)),
+        })
+    }
+}
+
+impl Encodable for StoredCookie {
+    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        s.emit_struct("Cookie", 8, |s| {
+            try!(s.emit_struct_field("name", 0, |s| self.cookie.name.encode(s)));
+            try!(s.emit_struct_field("value", 1, |s| self.cookie.value.encode(s)));
+            try!(s.emit_struct_field("domain", 2, |s| self.domain.encode(s)));
+            try!(s.emit_struct_field("path", 3, |s| self.path.encode(s)));
+            try!(s.emit_struct_field("host_only", 4, |s| self.host_only.encode(s)));
+            try!(s.emit_struct_field("secure", 5, |s| self.cookie.secure.encode(s)));
+            try!(s.emit_struct_field("http_only", 6, |s| self.cookie.httponly.encode(s)));
+            s.emit_struct_field("expires", 7, |s| self.expires.map(|t| t.sec).encode(s))
+        })
+    }
+}
+
+impl Decodable for StoredCookie {
+    fn decode<D: Decoder>(d: &mut D) -> Result<StoredCookie, D::Error> {
+        d.read_struct("Cookie", 8, |d| {
+            let name = try!(d.read_struct_field("name", 0, Decodable::decode));
+            let value = try!(d.read_struct_field("value", 1, Decodable::decode));
+            let domain: String = try!(d.read_struct_field("domain", 2, Decodable::decode));
+            let path: String = try!(d.read_struct_field("path", 3, Decodable::decode));
+            let host_only = try!(d.read_struct_field("host_only", 4, Decodable::decode));
+            let secure = try!(d.read_struct_field("secure", 5, Decodable::decode));
+            let http_only = try!(d.read_struct_field("http_only", 6, Decodable::decode));
+            let expires: Option<i64> = try!(d.read_struct_field("expires", 7, Decodable::decode));
+            let domain = domain.trim_left_matches('.').to_ascii_lowercase();
+            if domain.is_empty() || !path.starts_with('/') {
+                return Err(d.error("invalid cookie domain or path"));
+            }
+
+            let mut cookie = CookiePair::new(name, value);
+            cookie.secure = secure;
+            cookie.httponly = http_only;
+            Ok(StoredCookie {
+                cookie: cookie,
+                domain: domain,
+                path: path,
+                host_only: host_only,
+                expires: expires.map(|sec| Timespec::new(sec, 0)),
+            })
+        })
+    }
+}
+
+/// The longest a cookie is kept, whatever its `Max-Age`, as in RFC 6265bis.
+/// This also keeps a huge `Max-Age` from overflowing the expiry time.
+const MAX_AGE: u64 = 400 * 24 * 60 * 60;
+
+/// curl marks `HttpOnly` cookies by prefixing their line in `cookies.txt`.
+const HTTP_ONLY_PREFIX: &'static str = "#HttpOnly_";
+
+fn parse_netscape_line(line: &str, httponly: bool) -> Option<StoredCookie> {
+    fn flag(val: &str) -> Option<bool> {
+        match val {
+            "TRUE" => Some(true),
+            "FALSE" => Some(false),
+            _ => None
+        }
+    }
+
+    let fields = line.split('\t').collect::<Vec<_>>();
+    if fields.len() != 7 {
+        return None;
+    }
+    let include_subdomains = match flag(fields[1]) {
+        Some(val) => val,
+        None => return None
+    };
+    let secure = match flag(fields[3]) {
+        Some(val) => val,
+        None => return None
+    };
+    let expires = match fields[4].parse::<i64>() {
+        Ok(0) => None,
+        Ok(sec) => Some(Timespec::new(sec, 0)),
+        Err(_) => return None
+    };
+    let domain = fields[0].trim_left_matches('.').to_ascii_lowercase();
+    if domain.is_empty() || !fields[2].starts_with('/') {
+        return None;
+    }
+
+    let mut cookie = CookiePair::new(fields[5].to_owned(), fields[6].to_owned());
+    cookie.secure = secure;
+    cookie.httponly = httponly;
+    Some(StoredCookie {
+        cookie: cookie,
+        domain: domain,
+        path: fields[2].to_owned(),
+        host_only: !include_subdomains,
+        expires: expires,
+    })
+}
+
+/// Store a cookie, replacing any with the same name, domain and path.
+fn insert(stored: &mut Vec<StoredCookie>, cookie: StoredCookie, now: Timespec) {
+    stored.retain(|c| {
+        !(c.cookie.name == cookie.cookie.name && c.domain == cookie.domain && c.path == cookie.path)
+    });
+    if cookie.is_expired(now) {
+        trace!("removed expired cookie {:?}", cookie.cookie.name);
+    } else {
+        trace!("stored cookie {:?}", cookie.cookie.name);
+        stored.push(cookie);
+    }
+}
+
+fn request_host(url: &Url) -> Option<String> {
+    url.serialize_host().map(|host| {
+        host.trim_matches(|c| c == '[' || c == ']').to_ascii_lowercase()
+    })
+}
+
+/// The default-path of a cookie, as described in
+/// [RFC6265](http://tools.ietf.org/html/rfc6265#section-5.1.4).
+fn default_path(url: &Url) -> String {
+    let path = url.serialize_path().unwrap_or_else(|| "/".to_owned());
+    match path.rfind('/') {
+        Some(0) | None => "/".to_owned(),
+        Some(i) => path[..i].to_owned()
+    }
+}
+
+fn domain_matches(host: &str, domain: &str) -> bool {
+    if host == domain {
+        return true;
+    }
+    // IP addresses only ever match exactly
+    host.parse::<IpAddr>().is_err() &&
+        host.len() > domain.len() &&
+        host.ends_with(domain) &&
+        host.as_bytes()[host.len() - domain.len() - 1] == b'.'
+}
+
+fn path_matches(path: &str, cookie_path: &str) -> bool {
+    path == cookie_path ||
+        path.starts_with(cookie_path) &&
+        (cookie_path.ends_with('/') || path.as_bytes()[cookie_path.len()] == b'/')
+}
+
+#[cfg(test)]
+mod tests {
+    use cookie::Cookie as CookiePair;
+    use cookie::CookieJar;
+    use serialize::json;
+    use url::Url;
+
+    use super::CookieStore;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    fn names(cookies: Vec<CookiePair>) -> Vec<String> {
+        cookies.into_iter().map(|c| c.name).collect()
+    }
+
+    #[test]
+    fn test_host_only() {
+        let store = CookieStore::new();
+        store.store(&url("http://example.domain/"), &["a=1".parse().unwrap()]);
+        assert_eq!(names(store.matching(&url("http://example.domain/foo"))), vec!["a"]);
+        assert!(store.matching(&url("http://www.example.domain/")).is_empty());
+        assert!(store.matching(&url("http://other.domain/")).is_empty());
+    }
+
+    #[test]
+    fn test_domain() {
+        let store = CookieStore::new();
+        let cookies = vec![
+            "a=1; Domain=.example.domain".parse().unwrap(),
+            "b=2; Domain=other.domain".parse().unwrap(),
+        ];
+        store.store(&url("http://www.example.domain/"), &cookies);
+        assert_eq!(names(store.matching(&url("http://example.domain/"))), vec!["a"]);
+        assert_eq!(names(store.matching(&url("http://api.example.domain/"))), vec!["a"]);
+        assert!(store.matching(&url("http://other.domain/")).is_empty());
+    }
+
+    #[test]
+    fn test_top_level_domain() {
+        let store = CookieStore::new();
+        store.store(&url("http://example.com/"), &["a=1; Domain=com".parse().unwrap()]);
+        assert!(store.cookies().is_empty());
+
+        // a single label host may still set a cookie for itself
+        store.store(&url("http://localhost/"), &["b=2; Domain=localhost".parse().unwrap()]);
+        assert_eq!(names(store.matching(&url("http://localhost/"))), vec!["b"]);
+        assert!(store.matching(&url("http://api.localhost/")).is_empty());
+    }
+
+    #[test]
+    fn test_path() {
+        let store = CookieStore::new();
+        let cookies = vec![
+            "a=1; Path=/".parse().unwrap(),
+            "b=2; Path=/docs".parse().unwrap(),
+        ];
+        store.store(&url("http://example.domain/"), &cookies);
+        assert_eq!(names(store.matching(&url("http://example.domain/docs/web"))), vec!["b", "a"]);
+        assert_eq!(names(store.matching(&url("http://example.domain/docs"))), vec!["b", "a"]);
+        assert_eq!(names(store.matching(&url("http://example.domain/docsets"))), vec!["a"]);
+    }
+
+    #[test]
+    fn test_default_path() {
+        let store = CookieStore::new();
+        let mut cookie = CookiePair::new("a".to_owned(), "1".to_owned());
+        cookie.path = None;
+        store.store(&url("http://example.domain/docs/index.html"), &[cookie]);
+        assert_eq!(store.cookies()[0].path, Some("/docs".to_owned()));
+        assert!(store.matching(&url("http://example.domain/")).is_empty());
+        assert_eq!(names(store.matching(&url("http://example.domain/docs/"))), vec!["a"]);
+    }
+
+    #[test]
+    fn test_secure() {
+        let store = CookieStore::new();
+        store.store(&url("https://example.domain/"), &["a=1; Secure; HttpOnly".parse().unwrap()]);
+        assert!(store.matching(&url("http://example.domain/")).is_empty());
+        assert_eq!(names(store.matching(&url("https://example.domain/"))), vec!["a"]);
+    }
+
+    #[test]
+    fn test_expiry() {
+        let store = CookieStore::new();
+        store.store(&url("http://example.domain/"), &[
+            "a=1".parse().unwrap(),
+            "b=2; Max-Age=60".parse().unwrap(),
+            "c=3; Expires=Sun, 06 Nov 1994 08:49:37 GMT".parse().unwrap(),
+        ]);
+        assert_eq!(names(store.matching(&url("http://example.domain/"))), vec!["a", "b"]);
+
+        store.store(&url("http://example.domain/"),
+                    &["a=; Expires=Sun, 06 Nov 1994 08:49:37 GMT".parse().unwrap()]);
+        assert_eq!(names(store.matching(&url("http://example.domain/"))), vec!["b"]);
+    }
+
+    #[test]
+    fn test_huge_max_age() {
+        let store = CookieStore::new();
+        store.store(&url("http://example.domain/"),
+                    &["a=1; Max-Age=9223372036854775807".parse().unwrap()]);
+        assert_eq!(names(store.matching(&url("http://example.domain/"))), vec!["a"]);
+    }
+
+    #[test]
+    fn test_replace() {
+        let store = CookieStore::new();
+        store.store(&url("http://example.domain/"), &["a=1".parse().unwrap()]);
+        store.store(&url("http://example.domain/"), &["a=2".parse().unwrap()]);
+        let cookies = store.matching(&url("http://example.domain/"));
+        assert_eq!(cookies.len(), 1);
+        assert_eq!(cookies[0].value, "2");
+    }
+
+    #[test]
+    fn test_ip_host() {
+        let store = CookieStore::new();
+        store.store(&url("http://127.0.0.1/"), &["a=1; Domain=0.0.1".parse().unwrap()]);
+        assert!(store.cookies().is_empty());
+        store.store(&url("http://127.0.0.1/"), &["a=1".parse().unwrap()]);
+        assert_eq!(names(store.matching(&url("http://127.0.0.1/"))), vec!["a"]);
+    }
+
+    #[test]
+    fn test_shared_clones() {
+        let store = CookieStore::new();
+        store.clone().store(&url("http://example.domain/"), &["a=1".parse().unwrap()]);
+        assert_eq!(store.cookies().len(), 1);
+        store.clear();
+        assert!(store.cookies().is_empty());
+    }
+
+    #[test]
+    fn test_save_netscape() {
+        let store = CookieStore::new();
+        store.store(&url("https://www.example.domain/"), &[
+            "a=1".parse().unwrap(),
+            "b=2; Domain=example.domain; Path=/docs; Secure; HttpOnly".parse().unwrap(),
+            "c=3; Expires=Wed, 09 Jun 2100 10:18:14 GMT".parse().unwrap(),
+        ]);
+        let mut out = vec![];
+        store.save_netscape(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "\
+            # Netscape HTTP Cookie File\n\
+            www.example.domain\tFALSE\t/\tFALSE\t0\ta\t1\n\
+            #HttpOnly_.example.domain\tTRUE\t/docs\tTRUE\t0\tb\t2\n\
+            www.example.domain\tFALSE\t/\tFALSE\t4116219494\tc\t3\n");
+    }
+
+    #[test]
+    fn test_load_netscape() {
+        let store = CookieStore::new();
+        store.load_netscape(&b"\
+            # Netscape HTTP Cookie File\n\
+            \n\
+            www.example.domain\tFALSE\t/\tFALSE\t0\ta\t1\r\n\
+            #HttpOnly_.example.domain\tTRUE\t/docs\tTRUE\t4116219494\tb\t2\n\
+            .example.domain\tTRUE\t/\tFALSE\t784111777\texpired\t3\n"[..]).unwrap();
+
+        assert_eq!(names(store.matching(&url("http://www.example.domain/"))), vec!["a"]);
+        assert!(store.matching(&url("http://api.example.domain/docs")).is_empty());
+        assert_eq!(names(store.matching(&url("https://api.example.domain/docs"))), vec!["b"]);
+        let cookies = store.cookies();
+        assert_eq!(cookies.len(), 2);
+        assert!(cookies[1].httponly);
+    }
+
+    #[test]
+    fn test_netscape_round_trip() {
+        let store = CookieStore::new();
+        store.store(&url("http://example.domain/"), &[
+            "a=1; Max-Age=60".parse().unwrap(),
+            "b=2; Domain=example.domain; HttpOnly".parse().unwrap(),
+        ]);
+        let mut out = vec![];
+        store.save_netscape(&mut out).unwrap();
+
+        let loaded = CookieStore::new();
+        loaded.load_netscape(&out[..]).unwrap();
+        let mut saved = vec![];
+        loaded.save_netscape(&mut saved).unwrap();
+        assert_eq!(out, saved);
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let store = CookieStore::new();
+        store.store(&url("https://www.example.domain/"), &[
+            "a=1; Max-Age=60".parse().unwrap(),
+            "b=2; Domain=example.domain; Path=/docs; Secure; HttpOnly".parse().unwrap(),
+        ]);
+        let mut out = vec![];
+        store.save_json(&mut out).unwrap();
+
+        let loaded = CookieStore::new();
+        loaded.load_json(&out[..]).unwrap();
+        assert_eq!(names(loaded.matching(&url("https://www.example.domain/docs"))),
+                   vec!["b", "a"]);
+        assert!(loaded.matching(&url("http://api.example.domain/docs")).is_empty());
+        assert_eq!(names(loaded.matching(&url("https://api.example.domain/docs"))), vec!["b"]);
+        let mut saved = vec![];
+        loaded.save_json(&mut saved).unwrap();
+        assert_eq!(out, saved);
+
+        let decoded: CookieStore = json::decode(&String::from_utf8(out).unwrap()).unwrap();
+        assert_eq!(decoded.cookies().len(), 2);
+    }
+
+    #[test]
+    fn test_load_json() {
+        let store = CookieStore::new();
+        store.load_json(&br#"[
+            {"name": "a", "value": "1", "domain": "example.domain", "path": "/",
+             "host_only": true, "secure": false, "http_only": false, "expires": null},
+            {"name": "expired", "value": "2", "domain": "example.domain", "path": "/",
+             "host_only": false, "secure": false, "http_only": false, "expires": 784111777}
+        ]"#[..]).unwrap();
+        assert_eq!(names(store.matching(&url("http://example.domain/"))), vec!["a"]);
+        assert!(store.matching(&url("http://www.example.domain/")).is_empty());
+
+        assert!(store.load_json(&b"[{\"name\": \"a\"}]"[..]).is_err());
+        assert!(store.load_json(&br#"[
+            {"name": "a", "value": "1", "domain": "", "path": "/",
+             "host_only": true, "secure": false, "http_only": false, "expires": null}
+        ]"#[..]).is_err());
+    }
+
+    #[test]
+    fn test_load_netscape_invalid() {
+        let store = CookieStore::new();
+        assert!(store.load_netscape(&b"example.domain\tFALSE\t/\n"[..]).is_err());
+        assert!(store.load_netscape(&b"example.domain\tMAYBE\t/\tFALSE\t0\ta\t1\n"[..]).is_err());
+    }
+
+    #[test]
+    fn test_apply_to_cookie_jar() {
+        let store = CookieStore::new();
+        store.store(&url("http://example.domain/"), &["a=1".parse().unwrap()]);
+        let mut jar = CookieJar::new(b"key");
+        store.apply_to_cookie_jar(&mut jar);
+        let cookie = jar.find("a").unwrap();
+        assert_eq!(cookie.value, "1");
+        assert_eq!(cookie.domain, Some("example.domain".to_owned()));
+    }
+}
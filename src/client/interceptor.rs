@@ -0,0 +1,116 @@
+//! Client request interceptors.
+//!
+//! An `Interceptor` is called for every request a `Client` sends, including
+//! each redirect that is followed. It can change the URL and headers of the
+//! request before it is sent, answer the request itself instead, and
+//! inspect or replace the `Response`.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::time::Instant;
+//! use hyper::Client;
+//! use hyper::client::{Next, Outgoing, Response};
+//! use hyper::header::Authorization;
+//!
+//! let mut client = Client::new();
+//! client.add_interceptor(|req: &mut Outgoing, next: Next| -> hyper::Result<Response> {
+//!     req.headers.set(Authorization("Bearer secret".to_owned()));
+//!     let start = Instant::now();
+//!     let res = next.send(req);
+//!     println!("{} {} took {:?}", req.method(), req.url, start.elapsed());
+//!     res
+//! });
+//! let res = client.get("http://example.domain").send().unwrap();
+//! ```
+use header::Headers;
+use method::Method;
+use Url;
+
+use super::{Body, Client, Response, Timeouts};
+
+/// Intercepts the requests sent by a `Client`.
+///
+/// Interceptors run in the order they were added to the `Client`, each
+/// one around those added after it. Retries of a request happen within
+/// `Next::send`, so an interceptor is called once for each redirect, but
+/// not for each retry.
+pub trait Interceptor: Send + Sync {
+    /// Send `req` by passing it on to `next`, or answer it with a response
+    /// of its own.
+    ///
+    /// Changes to `req` only apply to this request, and not to a redirect
+    /// that follows it. A relative `Location` is resolved against the URL
+    /// the request was sent to.
+    fn intercept(&self, req: &mut Outgoing, next: Next) -> ::Result<Response>;
+}
+
+impl<F> Interceptor for F where F: Fn(&mut Outgoing, Next) -> ::Result<Response> + Send + Sync {
+    fn intercept(&self, req: &mut Outgoing, next: Next) -> ::Result<Response> {
+        (*self)(req, next)
+    }
+}
+
+/// A request about to be sent, as seen by an `Interceptor`.
+///
+/// The `Client` adds some headers of its own after the interceptors have
+/// run, such as `Cookie` and `Content-Length`.
+#[derive(Debug)]
+pub struct Outgoing {
+    method: Method,
+    /// The URL the request is sent to.
+    pub url: Url,
+    /// The headers sent with the request.
+    pub headers: Headers,
+}
+
+impl Outgoing {
+    /// The method of the request.
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+}
+
+/// The rest of the interceptors, and then the `Client`, that a request is
+/// passed on to.
+pub struct Next<'a> {
+    client: &'a Client,
+    interceptors: &'a [Box<Interceptor>],
+    body: Option<Body<'a>>,
+    can_have_body: bool,
+    timeouts: Timeouts,
+}
+
+impl<'a> Next<'a> {
+    /// Send the request, and receive its `Response`.
+    pub fn send(self, req: &mut Outgoing) -> ::Result<Response> {
+        match self.interceptors.split_first() {
+            Some((interceptor, rest)) => {
+                interceptor.intercept(req, Next {
+                    interceptors: rest,
+                    ..self
+                })
+            },
+            None => self.client.send_retrying(&req.method, &req.url, Some(&req.headers),
+                                              self.body, self.can_have_body, &self.timeouts)
+        }
+    }
+}
+
+/// Send a request through the interceptors of `client`.
+pub fn send<'a>(client: &'a Client, method: &Method, url: &Url, headers: Option<&Headers>,
+                body: Option<Body<'a>>, can_have_body: bool,
+                timeouts: &Timeouts) -> ::Result<Response> {
+    let mut req = Outgoing {
+        method: method.clone(),
+        url: url.clone(),
+        headers: headers.cloned().unwrap_or_else(Headers::new),
+    };
+    Next {
+        client: client,
+        interceptors: &client.interceptors,
+        body: body,
+        can_have_body: can_have_body,
+        timeouts: *timeouts,
+    }.send(&mut req)
+}
@@ -55,44 +55,71 @@
 //!     clone2.post("http://example.domain/post").body("foo=bar").send().unwrap();
 //! });
 //! ```
+use std::borrow::Cow;
 use std::default::Default;
+use std::error::Error as StdError;
+use std::fmt;
 use std::io::{self, copy, Read};
 use std::iter::Extend;
-
-#[cfg(feature = "timeouts")]
-use std::time::Duration;
-
-use url::UrlParser;
+use std::mem;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serialize::Encodable;
+use serialize::json;
+use url::{UrlParser, form_urlencoded};
 use url::ParseError as UrlError;
 
 use header::{Headers, Header, HeaderFormat};
-use header::{ContentLength, Location};
+use header::{AcceptEncoding, ContentLength, ContentType, Cookie, Encoding, Location};
+use header::ProxyAuthorization;
+use header::{SetCookie, qitem};
+use header::HttpDate;
 use method::Method;
-use net::{NetworkConnector, NetworkStream, Fresh};
+use net::{NetworkConnector, NetworkStream, HttpConnector, DefaultSsl};
+use status::StatusCode;
 use {Url};
 use Error;
 
+pub use self::body::OwnedBody;
+pub use self::cookies::CookieStore;
+pub use self::interceptor::{Interceptor, Next, Outgoing};
+pub use self::multipart::{Multipart, Part};
 pub use self::pool::Pool;
+pub use self::proxy::{Proxy, ProxyConfig, ProxyConnector, NoProxy};
 pub use self::request::Request;
 pub use self::response::Response;
 
+pub mod body;
+pub mod cookies;
+mod interceptor;
+pub mod multipart;
 pub mod pool;
+pub mod proxy;
 pub mod request;
 pub mod response;
 
 use http::Protocol;
 use http::h1::Http11Protocol;
 
+const DEFAULT_MAX_REDIRECTS: usize = 10;
+
 /// A Client to use additional features with Requests.
 ///
 /// Clients can handle things such as: redirect policy, connection pooling.
 pub struct Client {
     protocol: Box<Protocol + Send + Sync>,
     redirect_policy: RedirectPolicy,
-    #[cfg(feature = "timeouts")]
+    max_redirects: usize,
+    proxy: Option<ProxyConfig>,
+    cookie_store: Option<CookieStore>,
+    decompress: bool,
+    retry_policy: RetryPolicy,
+    connect_timeout: Option<Duration>,
     read_timeout: Option<Duration>,
-    #[cfg(feature = "timeouts")]
     write_timeout: Option<Duration>,
+    interceptors: Vec<Box<Interceptor>>,
 }
 
 impl Client {
@@ -107,29 +134,59 @@ impl Client {
         Client::with_connector(Pool::new(config))
     }
 
+    /// Create a new Client that sends its requests through an HTTP proxy.
+    pub fn with_http_proxy<H>(host: H, port: u16) -> Client
+    where H: Into<Cow<'static, str>> {
+        Client::with_proxy(Proxy::new(host, port))
+    }
+
+    /// Create a new Client that sends its requests through the given `Proxy`.
+    ///
+    /// Idle connections to the proxy are pooled, and shared by requests
+    /// to any origin server. HTTPS requests are tunneled through the proxy
+    /// using `CONNECT`.
+    pub fn with_proxy(proxy: Proxy) -> Client {
+        Client::with_proxy_config(proxy.into())
+    }
+
+    /// Create a new Client that picks a proxy for each request from the
+    /// `http_proxy`, `https_proxy`, `all_proxy` and `no_proxy` environment
+    /// variables.
+    ///
+    /// See `ProxyConfig::from_env` for how the variables are read.
+    pub fn with_env_proxy() -> Client {
+        Client::with_proxy_config(ProxyConfig::from_env())
+    }
+
+    /// Create a new Client that sends its requests through the proxies of
+    /// the given `ProxyConfig`.
+    pub fn with_proxy_config(config: ProxyConfig) -> Client {
+        let connector = ProxyConnector::new(HttpConnector::new(), DefaultSsl::default(), config.clone());
+        let mut client = Client::with_connector(Pool::with_connector(Default::default(), connector));
+        client.proxy = Some(config);
+        client
+    }
+
     /// Create a new client with a specific connector.
     pub fn with_connector<C, S>(connector: C) -> Client
     where C: NetworkConnector<Stream=S> + Send + Sync + 'static, S: NetworkStream + Send {
         Client::with_protocol(Http11Protocol::with_connector(connector))
     }
 
-    #[cfg(not(feature = "timeouts"))]
-    /// Create a new client with a specific `Protocol`.
-    pub fn with_protocol<P: Protocol + Send + Sync + 'static>(protocol: P) -> Client {
-        Client {
-            protocol: Box::new(protocol),
-            redirect_policy: Default::default(),
-        }
-    }
-
-    #[cfg(feature = "timeouts")]
     /// Create a new client with a specific `Protocol`.
     pub fn with_protocol<P: Protocol + Send + Sync + 'static>(protocol: P) -> Client {
         Client {
             protocol: Box::new(protocol),
             redirect_policy: Default::default(),
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            proxy: None,
+            cookie_store: None,
+            decompress: false,
+            retry_policy: Default::default(),
+            connect_timeout: None,
             read_timeout: None,
             write_timeout: None,
+            interceptors: vec![],
         }
     }
 
@@ -138,18 +195,65 @@ impl Client {
         self.redirect_policy = policy;
     }
 
+    /// Set the maximum number of redirects a request follows, after which
+    /// sending it fails with `Error::TooManyRedirects`.
+    ///
+    /// The default is 10.
+    pub fn set_max_redirects(&mut self, max: usize) {
+        self.max_redirects = max;
+    }
+
+    /// Set the RetryPolicy.
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry_policy = policy;
+    }
+
+    /// Set the CookieStore, to keep the cookies set by responses and send
+    /// them with later requests.
+    ///
+    /// A Client does not keep any cookies unless a store is set.
+    pub fn set_cookie_store(&mut self, store: CookieStore) {
+        self.cookie_store = Some(store);
+    }
+
+    /// The CookieStore of this Client, if one has been set.
+    pub fn cookie_store(&self) -> Option<&CookieStore> {
+        self.cookie_store.as_ref()
+    }
+
+    /// Set whether responses compressed with `gzip` or `deflate` should be
+    /// decoded.
+    ///
+    /// When enabled, requests advertise both encodings with `Accept-Encoding`,
+    /// unless the request sets that header itself, and the body of each
+    /// `Response` is decoded as it is read.
+    pub fn set_decompress(&mut self, decompress: bool) {
+        self.decompress = decompress;
+    }
+
+    /// Set the connect timeout value for all requests.
+    ///
+    /// See `RequestBuilder::connect_timeout`.
+    pub fn set_connect_timeout(&mut self, dur: Option<Duration>) {
+        self.connect_timeout = dur;
+    }
+
     /// Set the read timeout value for all requests.
-    #[cfg(feature = "timeouts")]
     pub fn set_read_timeout(&mut self, dur: Option<Duration>) {
         self.read_timeout = dur;
     }
 
     /// Set the write timeout value for all requests.
-    #[cfg(feature = "timeouts")]
     pub fn set_write_timeout(&mut self, dur: Option<Duration>) {
         self.write_timeout = dur;
     }
 
+    /// Add an `Interceptor`, to be called for every request, after those
+    /// already added.
+    pub fn add_interceptor<I: Interceptor + 'static>(&mut self, interceptor: I) {
+        self.interceptors.push(Box::new(interceptor));
+    }
+
     /// Build a Get request.
     pub fn get<U: IntoUrl>(&self, url: U) -> RequestBuilder {
         self.request(Method::Get, url)
@@ -189,8 +293,150 @@ impl Client {
             url: url.into_url(),
             body: None,
             headers: None,
+            timeouts: Timeouts {
+                connect: self.connect_timeout,
+                read: self.read_timeout,
+                write: self.write_timeout,
+                total: None,
+                deadline: None,
+            },
+        }
+    }
+
+    /// The proxy a request to this url should be forwarded by, if any.
+    ///
+    /// Only plain HTTP requests are forwarded by a proxy, others are
+    /// tunneled by the connector.
+    fn proxy_for(&self, url: &Url) -> ::Result<Option<&Proxy>> {
+        match self.proxy {
+            Some(ref config) if url.scheme == "http" => {
+                let (host, port) = try!(get_host_and_port(url));
+                Ok(config.proxy_for("http", &host, port))
+            },
+            _ => Ok(None)
+        }
+    }
+
+    /// Send a request, retrying it as the RetryPolicy allows.
+    fn send_retrying(&self, method: &Method, url: &Url, headers: Option<&Headers>,
+                     mut body: Option<Body>, can_have_body: bool,
+                     timeouts: &Timeouts) -> ::Result<Response> {
+        let policy = &self.retry_policy;
+        let mut retries = 0;
+        loop {
+            // a body that is read from a stream can't be sent again
+            let replay = match body {
+                Some(ref body) => body.replay().map(Some),
+                None => Some(None)
+            };
+            let can_retry = retries < policy.max_retries &&
+                method.idempotent() &&
+                replay.is_some();
+
+            let res = self.send_once(method, url, headers, body.take(), can_have_body, timeouts)
+                .map_err(timeout_error);
+            let delay = match res {
+                _ if !can_retry => None,
+                Err(ref e) if is_connection_error(e) => {
+                    debug!("connection error sending {:?} {}: {:?}", method, url, e);
+                    Some(policy.backoff(retries))
+                },
+                Ok(ref res) if policy.retry_unavailable &&
+                               res.status == StatusCode::ServiceUnavailable => {
+                    retry_after(&res.headers).and_then(|delay| {
+                        if delay <= policy.max_backoff {
+                            Some(delay)
+                        } else {
+                            None
+                        }
+                    })
+                },
+                _ => None
+            };
+            match (delay, replay) {
+                (Some(delay), Some(replay)) if timeouts.can_wait(delay) => {
+                    debug!("retrying {:?} {} in {:?}", method, url, delay);
+                    drop(res);
+                    thread::sleep(delay);
+                    retries += 1;
+                    body = replay;
+                },
+                _ => return res
+            }
         }
     }
+
+    /// Send a request once, without following redirects.
+    fn send_once(&self, method: &Method, url: &Url, headers: Option<&Headers>,
+                 body: Option<Body>, can_have_body: bool,
+                 timeouts: &Timeouts) -> ::Result<Response> {
+        let proxy = try!(self.proxy_for(url));
+        let connect_timeout = try!(timeouts.limit(timeouts.connect));
+        let message = match proxy {
+            Some(proxy) => {
+                // connections to the proxy can be shared by every origin,
+                // so they are pooled by the proxy's host and port
+                let mut message = try!(self.protocol.new_message_timeout(&proxy.host, proxy.port,
+                                                                        "http", connect_timeout));
+                message.set_proxied(true);
+                message
+            },
+            None => {
+                let (host, port) = try!(get_host_and_port(url));
+                try!(self.protocol.new_message_timeout(&host, port, &*url.scheme, connect_timeout))
+            }
+        };
+        let mut req = try!(Request::with_message(method.clone(), url.clone(), message));
+        headers.map(|headers| req.headers_mut().extend(headers.iter()));
+        if let Some(&Proxy { credentials: Some(ref credentials), .. }) = proxy {
+            req.headers_mut().set(ProxyAuthorization(credentials.clone()));
+        }
+        if self.decompress && !req.headers().has::<AcceptEncoding>() {
+            req.headers_mut().set(AcceptEncoding(vec![
+                qitem(Encoding::Gzip),
+                qitem(Encoding::Deflate),
+            ]));
+        }
+        if let Some(ref store) = self.cookie_store {
+            let cookies = store.matching(url);
+            if !cookies.is_empty() {
+                let mut header = req.headers().get::<Cookie>().cloned()
+                    .unwrap_or_else(|| Cookie(vec![]));
+                header.extend(cookies);
+                req.headers_mut().set(header);
+            }
+        }
+        // set even without timeouts, since a pooled stream may still have
+        // those of an earlier request
+        try!(req.set_write_timeout(try!(timeouts.limit(timeouts.write))));
+        try!(req.set_read_timeout(try!(timeouts.limit(timeouts.read))));
+
+        match (can_have_body, body.as_ref()) {
+            (true, Some(body)) => match body.size() {
+                Some(size) => req.headers_mut().set(ContentLength(size)),
+                None => (), // chunked, Request will add it automatically
+            },
+            (true, None) => req.headers_mut().set(ContentLength(0)),
+            _ => () // neither
+        }
+        let mut streaming = try!(req.start());
+        if let Some(mut rdr) = body {
+            try!(copy(&mut rdr, &mut streaming));
+        }
+        let mut res = try!(streaming.send());
+        if let Some(deadline) = timeouts.deadline {
+            res.set_deadline(deadline, timeouts.read);
+        }
+        if self.decompress {
+            res.decode_content();
+        }
+        if let Some(ref store) = self.cookie_store {
+            if let Some(cookies) = res.headers.get::<SetCookie>() {
+                store.store(url, cookies);
+            }
+        }
+        Ok(res)
+    }
 }
 
 impl Default for Client {
@@ -215,14 +461,130 @@ pub struct RequestBuilder<'a> {
     url: Result<Url, UrlError>,
     headers: Option<Headers>,
     method: Method,
-    body: Option<Body<'a>>,
+    // The body is a result for the same reason, so that an error encoding
+    // it, such as from `json`, is reported from `send`.
+    body: Option<::Result<Body<'a>>>,
+    timeouts: Timeouts,
 }
 
 impl<'a> RequestBuilder<'a> {
 
     /// Set a request body to be sent.
     pub fn body<B: Into<Body<'a>>>(mut self, body: B) -> RequestBuilder<'a> {
-        self.body = Some(body.into());
+        self.body = Some(Ok(body.into()));
+        self
+    }
+
+    /// Set a body to be sent, encoded as JSON, and its `Content-Type`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use hyper::Client;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let client = Client::new();
+    /// let mut release = BTreeMap::new();
+    /// release.insert("version", "1.0.3");
+    /// let res = client.post("http://example.domain/releases").json(&release).send().unwrap();
+    /// ```
+    pub fn json<T: Encodable>(mut self, value: &T) -> RequestBuilder<'a> {
+        self.body = Some(json::encode(value).map(Into::into).map_err(From::from));
+        self.header(ContentType::json())
+    }
+
+    /// Set an `application/x-www-form-urlencoded` body to be sent, made of
+    /// the given pairs, and its `Content-Type`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use hyper::Client;
+    /// let client = Client::new();
+    /// let res = client.post("http://example.domain/login")
+    ///     .form(&[("user", "sean"), ("password", "hunter2")])
+    ///     .send()
+    ///     .unwrap();
+    /// ```
+    pub fn form<K, V>(self, pairs: &[(K, V)]) -> RequestBuilder<'a>
+    where K: AsRef<str>, V: AsRef<str> {
+        let body = form_urlencoded::serialize(pairs);
+        self.header(ContentType::form_url_encoded()).body(body)
+    }
+
+    /// Append the given pairs to the query string of the URL.
+    pub fn query<K, V>(mut self, pairs: &[(K, V)]) -> RequestBuilder<'a>
+    where K: AsRef<str>, V: AsRef<str> {
+        if let Ok(ref mut url) = self.url {
+            let pairs = form_urlencoded::serialize(pairs);
+            url.query = Some(match url.query.take() {
+                Some(ref query) if !query.is_empty() => format!("{}&{}", query, pairs),
+                _ => pairs
+            });
+        }
+        self
+    }
+
+    /// Set a `multipart/form-data` body to be sent, and the `Content-Type`
+    /// with its boundary.
+    pub fn multipart(self, multipart: Multipart) -> RequestBuilder<'a> {
+        let size = multipart.size();
+        self.header(multipart.content_type()).body(OwnedBody::reader(multipart, size))
+    }
+
+    /// Set how long to wait for a connection to be established.
+    ///
+    /// This overrides the Client's connect timeout, and applies to each
+    /// redirect that is followed.
+    ///
+    /// With a `Pool` that is full, the timeout also covers waiting for a
+    /// connection to become available, so a request can fail with
+    /// `Error::Timeout` without having tried to connect. The pool's
+    /// `checkout_timeout` bounds that wait as well, whichever ends first.
+    pub fn connect_timeout(mut self, timeout: Duration) -> RequestBuilder<'a> {
+        self.timeouts.connect = Some(timeout);
+        self
+    }
+
+    /// Set how long each read of the response may wait, overriding the
+    /// Client's read timeout.
+    pub fn read_timeout(mut self, timeout: Duration) -> RequestBuilder<'a> {
+        self.timeouts.read = Some(timeout);
+        self
+    }
+
+    /// Set how long each write of the request may wait, overriding the
+    /// Client's write timeout.
+    pub fn write_timeout(mut self, timeout: Duration) -> RequestBuilder<'a> {
+        self.timeouts.write = Some(timeout);
+        self
+    }
+
+    /// Set how long the whole request may take, from connecting to reading
+    /// the last of the response body, including any redirects and retries.
+    ///
+    /// Sending fails with `Error::Timeout` once the time is up, and reading
+    /// the body of the `Response` fails with an `io::Error` of kind
+    /// `TimedOut`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use hyper::Client;
+    /// use std::io::Read;
+    /// use std::time::Duration;
+    ///
+    /// let client = Client::new();
+    /// let mut res = client.get("http://example.domain")
+    ///     .connect_timeout(Duration::from_secs(2))
+    ///     .timeout(Duration::from_secs(10))
+    ///     .send()
+    ///     .unwrap();
+    /// let mut body = String::new();
+    /// res.read_to_string(&mut body).unwrap();
+    /// ```
+    pub fn timeout(mut self, timeout: Duration) -> RequestBuilder<'a> {
+        self.timeouts.total = Some(timeout);
         self
     }
 
@@ -249,57 +611,42 @@ impl<'a> RequestBuilder<'a> {
     }
 
     /// Execute this request and receive a Response back.
+    ///
+    /// Redirects are followed as the Client's `RedirectPolicy` allows, up to
+    /// its maximum number of redirects. A `303 See Other`, or a `301` or `302`
+    /// in response to a `POST`, is followed with a `GET` that has no body.
+    /// Other redirects repeat the method and body, and are not followed when
+    /// the body can't be sent again. `Authorization` and `Cookie` headers are
+    /// not sent to another origin, or from `https` to `http`.
+    ///
+    /// The request, and each redirect, is passed through the Client's
+    /// interceptors.
     pub fn send(self) -> ::Result<Response> {
-        let RequestBuilder { client, method, url, headers, body } = self;
+        let RequestBuilder { client, mut method, url, mut headers, body, mut timeouts } = self;
         let mut url = try!(url);
         trace!("send {:?} {:?}", method, url);
+        timeouts.deadline = timeouts.total.map(|total| Instant::now() + total);
 
-        let can_have_body = match &method {
-            &Method::Get | &Method::Head => false,
-            _ => true
-        };
-
-        let mut body = if can_have_body {
-            body
-        } else {
-            None
+        let mut body = match body {
+            Some(body) if can_have_body(&method) => Some(try!(body)),
+            _ => None
         };
+        let mut history = vec![];
 
         loop {
-            let message = {
-                let (host, port) = try!(get_host_and_port(&url));
-                try!(client.protocol.new_message(&host, port, &*url.scheme))
-            };
-            let mut req = try!(Request::with_message(method.clone(), url.clone(), message));
-            headers.as_ref().map(|headers| req.headers_mut().extend(headers.iter()));
-
-            #[cfg(not(feature = "timeouts"))]
-            fn set_timeouts(_req: &mut Request<Fresh>, _client: &Client) -> ::Result<()> {
-                Ok(())
-            }
-
-            #[cfg(feature = "timeouts")]
-            fn set_timeouts(req: &mut Request<Fresh>, client: &Client) -> ::Result<()> {
-                try!(req.set_write_timeout(client.write_timeout));
-                try!(req.set_read_timeout(client.read_timeout));
-                Ok(())
-            }
-
-            try!(set_timeouts(&mut req, &client));
-
-            match (can_have_body, body.as_ref()) {
-                (true, Some(body)) => match body.size() {
-                    Some(size) => req.headers_mut().set(ContentLength(size)),
-                    None => (), // chunked, Request will add it automatically
-                },
-                (true, None) => req.headers_mut().set(ContentLength(0)),
-                _ => () // neither
-            }
-            let mut streaming = try!(req.start());
-            body.take().map(|mut rdr| copy(&mut rdr, &mut streaming));
-            let res = try!(streaming.send());
-            if !res.status.is_redirection() {
-                return Ok(res)
+            let replay = body.as_ref().map(|body| body.replay());
+            let mut res = try!(interceptor::send(client, &method, &url, headers.as_ref(),
+                                                 body.take(), can_have_body(&method), &timeouts));
+            // an interceptor may have sent the request somewhere else
+            url = res.url.clone();
+            res.history = history;
+            match res.status {
+                StatusCode::MovedPermanently |
+                StatusCode::Found |
+                StatusCode::SeeOther |
+                StatusCode::TemporaryRedirect |
+                StatusCode::PermanentRedirect => (),
+                _ => return Ok(res)
             }
             debug!("redirect code {:?} for {}", res.status, url);
 
@@ -311,7 +658,6 @@ impl<'a> RequestBuilder<'a> {
                     }
                     None => {
                         debug!("no Location header");
-                        // could be 304 Not Modified?
                         None
                     }
                 };
@@ -320,23 +666,111 @@ impl<'a> RequestBuilder<'a> {
                     None => return Ok(res)
                 }
             };
-            url = match loc {
+            let next = match loc {
                 Ok(u) => u,
                 Err(e) => {
                     debug!("Location header had invalid URI: {:?}", e);
                     return Ok(res);
                 }
             };
-            match client.redirect_policy {
-                // separate branches because they can't be one
-                RedirectPolicy::FollowAll => (), //continue
-                RedirectPolicy::FollowIf(cond) if cond(&url) => (), //continue
-                _ => return Ok(res),
+
+            let next_method = redirect_method(&method, res.status);
+            let changes_to_get = next_method == Method::Get && method != Method::Get;
+            let next_body = match replay {
+                Some(_) if changes_to_get => None,
+                Some(Some(replay)) => Some(replay),
+                Some(None) => {
+                    debug!("body can't be sent again, not following redirect");
+                    return Ok(res);
+                },
+                None => None
+            };
+
+            res.history.push(url.clone());
+            let action = client.redirect_policy.redirect(&Redirect {
+                url: &next,
+                previous: &res.history,
+                status: res.status,
+                headers: &res.headers,
+            });
+            match action {
+                RedirectAction::Follow => (),
+                RedirectAction::Stop => {
+                    res.history.pop();
+                    return Ok(res);
+                },
+                RedirectAction::Error(e) => return Err(Error::Redirect(e)),
+            }
+            if res.history.len() > client.max_redirects {
+                debug!("stopped after {} redirects", client.max_redirects);
+                return Err(Error::TooManyRedirects);
+            }
+
+            if changes_to_get {
+                debug!("redirect changes {:?} to GET", method);
+                if let Some(ref mut headers) = headers {
+                    for name in &BODY_HEADERS {
+                        headers.remove_raw(name);
+                    }
+                }
             }
+            if is_insecure_redirect(&url, &next) {
+                debug!("redirect to {} leaves origin, removing credentials", next);
+                if let Some(ref mut headers) = headers {
+                    for name in &CREDENTIAL_HEADERS {
+                        headers.remove_raw(name);
+                    }
+                }
+            }
+            history = mem::replace(&mut res.history, vec![]);
+            body = next_body;
+            method = next_method;
+            url = next;
         }
     }
 }
 
+/// Headers describing a request body, removed when a redirect drops the body.
+const BODY_HEADERS: [&'static str; 4] = [
+    "Content-Encoding",
+    "Content-Length",
+    "Content-Type",
+    "Transfer-Encoding",
+];
+
+/// Headers that aren't sent to a different origin when following a redirect.
+const CREDENTIAL_HEADERS: [&'static str; 3] = [
+    "Authorization",
+    "Cookie",
+    "Proxy-Authorization",
+];
+
+fn can_have_body(method: &Method) -> bool {
+    match *method {
+        Method::Get | Method::Head => false,
+        _ => true
+    }
+}
+
+/// The method to use when following a redirect, per RFC 7231, section 6.4.
+fn redirect_method(method: &Method, status: StatusCode) -> Method {
+    match (status, method) {
+        (StatusCode::SeeOther, &Method::Head) => Method::Head,
+        (StatusCode::SeeOther, _) => Method::Get,
+        (StatusCode::MovedPermanently, &Method::Post) |
+        (StatusCode::Found, &Method::Post) => Method::Get,
+        _ => method.clone()
+    }
+}
+
+/// Whether a redirect from `prev` to `next` goes to another origin, which
+/// includes going from `https` to `http`.
+fn is_insecure_redirect(prev: &Url, next: &Url) -> bool {
+    prev.scheme != next.scheme ||
+        prev.host() != next.host() ||
+        prev.port_or_default() != next.port_or_default()
+}
+
 /// An enum of possible body types for a Request.
 pub enum Body<'a> {
     /// A Reader does not necessarily know it's size, so it is chunked.
@@ -345,6 +779,8 @@ pub enum Body<'a> {
     SizedBody(&'a mut (Read + 'a), u64),
     /// A String has a size, and uses Content-Length.
     BufBody(&'a [u8] , usize),
+    /// An owned body, which may be sent again to follow a redirect.
+    OwnedBody(OwnedBody),
 }
 
 impl<'a> Body<'a> {
@@ -352,6 +788,17 @@ impl<'a> Body<'a> {
         match *self {
             Body::SizedBody(_, len) => Some(len),
             Body::BufBody(_, len) => Some(len as u64),
+            Body::OwnedBody(ref body) => body.size(),
+            _ => None
+        }
+    }
+
+    /// A copy of this body that can be sent again, if it is not read from
+    /// a stream.
+    fn replay(&self) -> Option<Body<'a>> {
+        match *self {
+            Body::BufBody(buf, len) => Some(Body::BufBody(buf, len)),
+            Body::OwnedBody(ref body) => body.try_clone().map(Body::OwnedBody),
             _ => None
         }
     }
@@ -364,6 +811,7 @@ impl<'a> Read for Body<'a> {
             Body::ChunkedBody(ref mut r) => r.read(buf),
             Body::SizedBody(ref mut r, _) => r.read(buf),
             Body::BufBody(ref mut r, _) => Read::read(r, buf),
+            Body::OwnedBody(ref mut r) => r.read(buf),
         }
     }
 }
@@ -389,6 +837,27 @@ impl<'a> Into<Body<'a>> for &'a String {
     }
 }
 
+impl<'a> Into<Body<'a>> for Vec<u8> {
+    #[inline]
+    fn into(self) -> Body<'a> {
+        Body::OwnedBody(self.into())
+    }
+}
+
+impl<'a> Into<Body<'a>> for String {
+    #[inline]
+    fn into(self) -> Body<'a> {
+        Body::OwnedBody(self.into())
+    }
+}
+
+impl<'a> From<OwnedBody> for Body<'a> {
+    #[inline]
+    fn from(body: OwnedBody) -> Body<'a> {
+        Body::OwnedBody(body)
+    }
+}
+
 impl<'a, R: Read> From<&'a mut R> for Body<'a> {
     #[inline]
     fn from(r: &'a mut R) -> Body<'a> {
@@ -421,7 +890,6 @@ impl<'a> IntoUrl for &'a String {
 }
 
 /// Behavior regarding how to handle redirects within a Client.
-#[derive(Copy)]
 pub enum RedirectPolicy {
     /// Don't follow any redirects.
     FollowNone,
@@ -429,12 +897,70 @@ pub enum RedirectPolicy {
     FollowAll,
     /// Follow a redirect if the contained function returns true.
     FollowIf(fn(&Url) -> bool),
+    /// Decide what to do with each redirect using the contained function.
+    ///
+    /// Use `RedirectPolicy::custom` to create this policy.
+    Custom(Arc<Fn(&Redirect) -> RedirectAction + Send + Sync>),
+}
+
+impl RedirectPolicy {
+    /// Create a policy that decides what to do with each redirect by calling
+    /// the given function, which can capture state.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hyper::client::{Client, RedirectAction, RedirectPolicy};
+    ///
+    /// let mut client = Client::new();
+    /// client.set_redirect_policy(RedirectPolicy::custom(|redirect| {
+    ///     if redirect.previous().len() > 5 {
+    ///         RedirectAction::Error("redirected too often".into())
+    ///     } else if redirect.url().domain() == Some("example.domain") {
+    ///         RedirectAction::Follow
+    ///     } else {
+    ///         RedirectAction::Stop
+    ///     }
+    /// }));
+    /// ```
+    pub fn custom<F>(policy: F) -> RedirectPolicy
+    where F: Fn(&Redirect) -> RedirectAction + Send + Sync + 'static {
+        RedirectPolicy::Custom(Arc::new(policy))
+    }
+
+    fn redirect(&self, redirect: &Redirect) -> RedirectAction {
+        match *self {
+            RedirectPolicy::FollowNone => RedirectAction::Stop,
+            RedirectPolicy::FollowAll => RedirectAction::Follow,
+            RedirectPolicy::FollowIf(cond) => if cond(redirect.url()) {
+                RedirectAction::Follow
+            } else {
+                RedirectAction::Stop
+            },
+            RedirectPolicy::Custom(ref policy) => policy(redirect),
+        }
+    }
 }
 
-// This is a hack because of upstream typesystem issues.
 impl Clone for RedirectPolicy {
     fn clone(&self) -> RedirectPolicy {
-        *self
+        match *self {
+            RedirectPolicy::FollowNone => RedirectPolicy::FollowNone,
+            RedirectPolicy::FollowAll => RedirectPolicy::FollowAll,
+            RedirectPolicy::FollowIf(cond) => RedirectPolicy::FollowIf(cond),
+            RedirectPolicy::Custom(ref policy) => RedirectPolicy::Custom(policy.clone()),
+        }
+    }
+}
+
+impl fmt::Debug for RedirectPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RedirectPolicy::FollowNone => f.write_str("FollowNone"),
+            RedirectPolicy::FollowAll => f.write_str("FollowAll"),
+            RedirectPolicy::FollowIf(_) => f.write_str("FollowIf"),
+            RedirectPolicy::Custom(_) => f.write_str("Custom"),
+        }
     }
 }
 
@@ -444,6 +970,181 @@ impl Default for RedirectPolicy {
     }
 }
 
+/// A redirect response, as seen by a `RedirectPolicy`.
+#[derive(Debug)]
+pub struct Redirect<'a> {
+    url: &'a Url,
+    previous: &'a [Url],
+    status: StatusCode,
+    headers: &'a Headers,
+}
+
+impl<'a> Redirect<'a> {
+    /// The URL the response redirects to.
+    pub fn url(&self) -> &Url {
+        self.url
+    }
+
+    /// The URLs requested so far, in order. The last of them is the one
+    /// that responded with this redirect.
+    pub fn previous(&self) -> &[Url] {
+        self.previous
+    }
+
+    /// The status code of the redirect response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The headers of the redirect response.
+    pub fn headers(&self) -> &Headers {
+        self.headers
+    }
+}
+
+/// What a `RedirectPolicy` decides to do with a redirect.
+#[derive(Debug)]
+pub enum RedirectAction {
+    /// Follow the redirect.
+    Follow,
+    /// Don't follow the redirect, and return the redirect response.
+    Stop,
+    /// Don't follow the redirect, and fail the request with
+    /// `Error::Redirect` holding this error.
+    Error(Box<StdError + Send + Sync>),
+}
+
+/// Behavior regarding retrying failed requests within a Client.
+///
+/// Only requests with an idempotent `Method` are retried, and only when their
+/// body, if any, can be sent again. A request is retried when sending it
+/// fails with an IO error, such as when the server has closed a pooled
+/// connection, and optionally when the server responds with
+/// `503 Service Unavailable` and a `Retry-After` header.
+///
+/// The default policy does not retry.
+///
+/// # Example
+///
+/// ```
+/// use hyper::client::{Client, RetryPolicy};
+///
+/// let mut client = Client::new();
+/// client.set_retry_policy(RetryPolicy {
+///     max_retries: 3,
+///     ..Default::default()
+/// });
+/// ```
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// The most times a request is retried.
+    pub max_retries: u32,
+    /// How long to wait before the first retry. The wait doubles with each
+    /// following retry.
+    pub backoff: Duration,
+    /// The longest to wait before a retry. A `Retry-After` asking for a
+    /// longer wait is not retried.
+    pub max_backoff: Duration,
+    /// Whether to retry `503 Service Unavailable` responses that have a
+    /// `Retry-After` header.
+    pub retry_unavailable: bool,
+}
+
+impl RetryPolicy {
+    fn backoff(&self, retries: u32) -> Duration {
+        let mut backoff = self.backoff;
+        for _ in 0..retries {
+            backoff = backoff + backoff;
+            if backoff >= self.max_backoff {
+                return self.max_backoff;
+            }
+        }
+        backoff
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 0,
+            backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            retry_unavailable: false,
+        }
+    }
+}
+
+/// The timeouts of a request, and the time by which it must be done.
+#[derive(Clone, Copy, Debug)]
+struct Timeouts {
+    connect: Option<Duration>,
+    read: Option<Duration>,
+    write: Option<Duration>,
+    total: Option<Duration>,
+    deadline: Option<Instant>,
+}
+
+impl Timeouts {
+    /// Shorten `timeout` to the time left until the deadline, failing with
+    /// `Error::Timeout` if there is none left.
+    fn limit(&self, timeout: Option<Duration>) -> ::Result<Option<Duration>> {
+        let deadline = match self.deadline {
+            Some(deadline) => deadline,
+            None => return Ok(timeout)
+        };
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(Error::Timeout);
+        }
+        let remaining = deadline - now;
+        Ok(Some(match timeout {
+            Some(timeout) if timeout < remaining => timeout,
+            _ => remaining
+        }))
+    }
+
+    /// Whether there is time to wait for `delay` before the deadline.
+    fn can_wait(&self, delay: Duration) -> bool {
+        self.deadline.map_or(true, |deadline| Instant::now() + delay < deadline)
+    }
+}
+
+/// Report IO errors from a socket timing out as `Error::Timeout`.
+fn timeout_error(err: Error) -> Error {
+    match err {
+        Error::Io(ref e) if e.kind() == io::ErrorKind::TimedOut ||
+                            e.kind() == io::ErrorKind::WouldBlock => Error::Timeout,
+        err => err
+    }
+}
+
+fn is_connection_error(err: &Error) -> bool {
+    match *err {
+        Error::Io(..) => true,
+        _ => false
+    }
+}
+
+/// The wait requested by a `Retry-After` header, in either seconds or as an
+/// `HttpDate`.
+fn retry_after(headers: &Headers) -> Option<Duration> {
+    let raw = match headers.get_raw("Retry-After") {
+        Some(raw) if raw.len() == 1 => &raw[0],
+        _ => return None
+    };
+    let value = match ::std::str::from_utf8(raw) {
+        Ok(value) => value.trim(),
+        Err(_) => return None
+    };
+    if let Ok(secs) = value.parse() {
+        return Some(Duration::from_secs(secs));
+    }
+    value.parse::<HttpDate>().ok().map(|HttpDate(tm)| {
+        let secs = (tm.to_timespec() - ::time::get_time()).num_seconds();
+        Duration::from_secs(if secs > 0 { secs as u64 } else { 0 })
+    })
+}
+
 fn get_host_and_port(url: &Url) -> ::Result<(String, u16)> {
     let host = match url.serialize_host() {
         Some(host) => host,
@@ -460,11 +1161,23 @@ fn get_host_and_port(url: &Url) -> ::Result<(String, u16)> {
 
 #[cfg(test)]
 mod tests {
-    use std::io::Read;
-    use header::Server;
-    use super::{Client, RedirectPolicy};
-    use super::pool::Pool;
+    use std::io::{self, Read, Write};
+    use std::net::SocketAddr;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+    use cookie::Cookie as CookiePair;
+    use header::{Authorization, ContentType, Cookie, Headers, Server};
+    use method::Method;
+    use mock::{CloneableMockStream, MockStream};
+    use net::{NetworkConnector, NetworkStream};
+    use status::StatusCode;
+    use super::{Client, CookieStore, Multipart, OwnedBody, RedirectAction, RedirectPolicy};
+    use super::{Next, Outgoing, Response, RetryPolicy};
+    use super::{Proxy, ProxyConfig, NoProxy};
+    use super::pool::{self, Pool};
     use url::Url;
+    use Error;
 
     mock_connector!(MockRedirectPolicy {
         "http://127.0.0.1" =>       "HTTP/1.1 301 Redirect\r\n\
@@ -511,6 +1224,438 @@ mod tests {
         assert_eq!(res.headers.get(), Some(&Server("mock2".to_owned())));
     }
 
+    #[test]
+    fn test_redirect_history() {
+        let client = Client::with_connector(MockRedirectPolicy);
+        let res = client.get("http://127.0.0.1").send().unwrap();
+        assert_eq!(res.url, Url::parse("https://127.0.0.3").unwrap());
+        assert_eq!(res.history, vec![
+            Url::parse("http://127.0.0.1").unwrap(),
+            Url::parse("http://127.0.0.2").unwrap(),
+        ]);
+    }
+
+    #[test]
+    fn test_redirect_custom() {
+        let seen = Arc::new(Mutex::new(vec![]));
+        let seen2 = seen.clone();
+        let mut client = Client::with_connector(MockRedirectPolicy);
+        client.set_redirect_policy(RedirectPolicy::custom(move |redirect| {
+            seen2.lock().unwrap().push((redirect.status(), redirect.previous().len()));
+            assert_eq!(redirect.previous()[0], Url::parse("http://127.0.0.1").unwrap());
+            if redirect.url().scheme == "https" {
+                RedirectAction::Stop
+            } else {
+                RedirectAction::Follow
+            }
+        }));
+        let res = client.get("http://127.0.0.1").send().unwrap();
+        assert_eq!(res.status, StatusCode::Found);
+        assert_eq!(res.headers.get(), Some(&Server("mock2".to_owned())));
+        assert_eq!(res.history, vec![Url::parse("http://127.0.0.1").unwrap()]);
+        assert_eq!(*seen.lock().unwrap(), vec![
+            (StatusCode::MovedPermanently, 1),
+            (StatusCode::Found, 2),
+        ]);
+    }
+
+    #[test]
+    fn test_redirect_custom_error() {
+        let mut client = Client::with_connector(MockRedirectPolicy);
+        client.set_redirect_policy(RedirectPolicy::custom(|redirect| {
+            match redirect.headers().get::<Server>() {
+                Some(server) if server.0 == "mock1" => RedirectAction::Error("no mock1".into()),
+                _ => RedirectAction::Follow
+            }
+        }));
+        match client.get("http://127.0.0.1").send() {
+            Err(Error::Redirect(e)) => assert_eq!(e.to_string(), "no mock1"),
+            other => panic!("expected Redirect error, got {:?}", other)
+        }
+    }
+
+    mock_connector!(MockTemporaryRedirect {
+        "http://127.0.0.1" =>       "HTTP/1.1 307 Temporary Redirect\r\n\
+                                     Location: http://127.0.0.2\r\n\
+                                     Server: mock1\r\n\
+                                     \r\n\
+                                    "
+        "http://127.0.0.2" =>       "HTTP/1.1 200 OK\r\n\
+                                     Server: mock2\r\n\
+                                     \r\n\
+                                    "
+    });
+
+    #[test]
+    fn test_redirect_307_replays_owned_body() {
+        let client = Client::with_connector(MockTemporaryRedirect);
+        let req = client.post("http://127.0.0.1").body(OwnedBody::from("foo=bar"));
+        let res = req.send().unwrap();
+        assert_eq!(res.headers.get(), Some(&Server("mock2".to_owned())));
+    }
+
+    #[test]
+    fn test_redirect_307_stream_body_not_followed() {
+        let client = Client::with_connector(MockTemporaryRedirect);
+        let mut body = io::Cursor::new(b"foo=bar".to_vec());
+        let res = client.post("http://127.0.0.1").body(&mut body).send().unwrap();
+        assert_eq!(res.status, StatusCode::TemporaryRedirect);
+        assert_eq!(res.headers.get(), Some(&Server("mock1".to_owned())));
+    }
+
+    mock_connector!(MockRedirectLoop {
+        "http://127.0.0.1" =>       "HTTP/1.1 302 Found\r\n\
+                                     Location: http://127.0.0.1\r\n\
+                                     \r\n\
+                                    "
+    });
+
+    #[test]
+    fn test_redirect_max_redirects() {
+        let mut client = Client::with_connector(MockRedirectLoop);
+        client.set_max_redirects(3);
+        match client.get("http://127.0.0.1").send() {
+            Err(Error::TooManyRedirects) => (),
+            other => panic!("expected TooManyRedirects, got {:?}", other)
+        }
+    }
+
+    /// Responds with each of the responses in turn, and keeps the streams
+    /// so that the requests written to them can be checked.
+    struct RecordingConnector {
+        responses: Mutex<Vec<&'static [u8]>>,
+        streams: Arc<Mutex<Vec<CloneableMockStream>>>,
+    }
+
+    impl RecordingConnector {
+        fn new(mut responses: Vec<&'static [u8]>)
+                -> (RecordingConnector, Arc<Mutex<Vec<CloneableMockStream>>>) {
+            responses.reverse();
+            let streams = Arc::new(Mutex::new(vec![]));
+            let connector = RecordingConnector {
+                responses: Mutex::new(responses),
+                streams: streams.clone(),
+            };
+            (connector, streams)
+        }
+    }
+
+    impl NetworkConnector for RecordingConnector {
+        type Stream = CloneableMockStream;
+
+        fn connect(&self, _: &str, _: u16, _: &str) -> ::Result<CloneableMockStream> {
+            let res = self.responses.lock().unwrap().pop()
+                .expect("RecordingConnector ran out of responses");
+            let stream = CloneableMockStream::with_stream(MockStream::with_input(res));
+            self.streams.lock().unwrap().push(stream.clone());
+            Ok(stream)
+        }
+    }
+
+    fn written(streams: &Mutex<Vec<CloneableMockStream>>, i: usize) -> String {
+        let streams = streams.lock().unwrap();
+        let stream = streams[i].inner.lock().unwrap();
+        String::from_utf8(stream.write.clone()).unwrap()
+    }
+
+    #[test]
+    fn test_redirect_post_becomes_get() {
+        let (connector, streams) = RecordingConnector::new(vec![
+            b"HTTP/1.1 302 Found\r\nLocation: /next\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\n\r\n",
+        ]);
+        let client = Client::with_connector(connector);
+        let mut body = io::Cursor::new(b"foo=bar".to_vec());
+        let res = client.post("http://127.0.0.1/")
+            .header(ContentType::form_url_encoded())
+            .body(&mut body)
+            .send().unwrap();
+        assert_eq!(res.status, StatusCode::Ok);
+
+        let req = written(&streams, 1);
+        assert!(req.starts_with("GET /next HTTP/1.1\r\n"), "{:?}", req);
+        assert!(!req.contains("Content-Type"), "{:?}", req);
+        assert!(!req.contains("foo=bar"), "{:?}", req);
+    }
+
+    #[test]
+    fn test_redirect_307_keeps_method_and_credentials() {
+        let (connector, streams) = RecordingConnector::new(vec![
+            b"HTTP/1.1 307 Temporary Redirect\r\nLocation: /next\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\n\r\n",
+        ]);
+        let client = Client::with_connector(connector);
+        client.put("http://127.0.0.1/")
+            .header(Authorization("secret".to_owned()))
+            .body("foo=bar")
+            .send().unwrap();
+
+        let req = written(&streams, 1);
+        assert!(req.starts_with("PUT /next HTTP/1.1\r\n"), "{:?}", req);
+        assert!(req.contains("Authorization: secret\r\n"), "{:?}", req);
+        assert!(req.ends_with("\r\n\r\nfoo=bar"), "{:?}", req);
+    }
+
+    #[test]
+    fn test_redirect_strips_credentials() {
+        let (connector, streams) = RecordingConnector::new(vec![
+            b"HTTP/1.1 301 Moved Permanently\r\nLocation: http://127.0.0.2/\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\n\r\n",
+        ]);
+        let client = Client::with_connector(connector);
+        client.get("https://127.0.0.1/")
+            .header(Authorization("secret".to_owned()))
+            .header(Cookie(vec![CookiePair::new("session".to_owned(), "1".to_owned())]))
+            .send().unwrap();
+
+        assert!(written(&streams, 0).contains("Authorization: secret\r\n"));
+        let req = written(&streams, 1);
+        assert!(!req.contains("Authorization"), "{:?}", req);
+        assert!(!req.contains("Cookie"), "{:?}", req);
+    }
+
+    #[test]
+    fn test_redirect_method() {
+        use super::redirect_method;
+        assert_eq!(redirect_method(&Method::Post, StatusCode::MovedPermanently), Method::Get);
+        assert_eq!(redirect_method(&Method::Post, StatusCode::Found), Method::Get);
+        assert_eq!(redirect_method(&Method::Put, StatusCode::Found), Method::Put);
+        assert_eq!(redirect_method(&Method::Put, StatusCode::SeeOther), Method::Get);
+        assert_eq!(redirect_method(&Method::Head, StatusCode::SeeOther), Method::Head);
+        assert_eq!(redirect_method(&Method::Post, StatusCode::TemporaryRedirect), Method::Post);
+        assert_eq!(redirect_method(&Method::Post, StatusCode::PermanentRedirect), Method::Post);
+    }
+
+    #[test]
+    fn test_is_insecure_redirect() {
+        use super::is_insecure_redirect;
+        let url = |s| Url::parse(s).unwrap();
+        assert!(!is_insecure_redirect(&url("http://a.com/x"), &url("http://a.com:80/y")));
+        assert!(is_insecure_redirect(&url("http://a.com/"), &url("https://a.com/")));
+        assert!(is_insecure_redirect(&url("https://a.com/"), &url("http://a.com/")));
+        assert!(is_insecure_redirect(&url("http://a.com/"), &url("http://b.com/")));
+        assert!(is_insecure_redirect(&url("http://a.com/"), &url("http://a.com:8080/")));
+    }
+
+    #[test]
+    fn test_multipart() {
+        let (connector, streams) = RecordingConnector::new(vec![b"HTTP/1.1 200 OK\r\n\r\n"]);
+        let client = Client::with_connector(connector);
+        let form = Multipart::with_boundary("XyZ").text("a", "1");
+        client.post("http://127.0.0.1/").multipart(form).send().unwrap();
+
+        let req = written(&streams, 0);
+        assert!(req.contains("Content-Type: multipart/form-data; boundary=XyZ\r\n"), "{:?}", req);
+        assert!(req.contains("Content-Length: 63\r\n"), "{:?}", req);
+        assert!(req.ends_with("\r\n\r\n1\r\n--XyZ--\r\n"), "{:?}", req);
+    }
+
+    #[test]
+    fn test_form_and_query() {
+        let (connector, streams) = RecordingConnector::new(vec![b"HTTP/1.1 200 OK\r\n\r\n"]);
+        let client = Client::with_connector(connector);
+        client.post("http://127.0.0.1/login?v=1")
+            .query(&[("next", "/home page")])
+            .form(&[("user", "sean"), ("pass", "a&b=c")])
+            .send().unwrap();
+
+        let req = written(&streams, 0);
+        assert!(req.starts_with("POST /login?v=1&next=%2Fhome+page HTTP/1.1\r\n"), "{:?}", req);
+        assert!(req.contains("Content-Type: application/x-www-form-urlencoded\r\n"), "{:?}", req);
+        assert!(req.contains("Content-Length: 24\r\n"), "{:?}", req);
+        assert!(req.ends_with("\r\n\r\nuser=sean&pass=a%26b%3Dc"), "{:?}", req);
+    }
+
+    #[test]
+    fn test_json() {
+        let (connector, streams) = RecordingConnector::new(vec![b"HTTP/1.1 200 OK\r\n\r\n"]);
+        let client = Client::with_connector(connector);
+        client.post("http://127.0.0.1/").json(&vec!["a", "b"]).send().unwrap();
+
+        let req = written(&streams, 0);
+        assert!(req.contains("Content-Type: application/json; charset=utf-8\r\n"), "{:?}", req);
+        assert!(req.contains("Content-Length: 9\r\n"), "{:?}", req);
+        assert!(req.ends_with("\r\n\r\n[\"a\",\"b\"]"), "{:?}", req);
+    }
+
+    #[test]
+    fn test_interceptor_every_redirect() {
+        let (connector, streams) = RecordingConnector::new(vec![
+            b"HTTP/1.1 302 Found\r\nLocation: /next\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\n\r\n",
+        ]);
+        let seen = Arc::new(Mutex::new(vec![]));
+        let seen2 = seen.clone();
+        let mut client = Client::with_connector(connector);
+        client.add_interceptor(move |req: &mut Outgoing, next: Next| -> ::Result<Response> {
+            seen2.lock().unwrap().push(req.url.serialize());
+            req.headers.set_raw("X-Request-Id", vec![b"42".to_vec()]);
+            req.url.query = Some("intercepted".to_owned());
+            next.send(req)
+        });
+        let res = client.get("http://127.0.0.1/").send().unwrap();
+        assert_eq!(res.status, StatusCode::Ok);
+        assert_eq!(*seen.lock().unwrap(), vec![
+            "http://127.0.0.1/".to_owned(),
+            "http://127.0.0.1/next".to_owned(),
+        ]);
+        for i in 0..2 {
+            let req = written(&streams, i);
+            assert!(req.contains("?intercepted HTTP/1.1\r\n"), "{:?}", req);
+            assert!(req.contains("X-Request-Id: 42\r\n"), "{:?}", req);
+        }
+    }
+
+    #[test]
+    fn test_interceptor_changes_host() {
+        let (connector, streams) = RecordingConnector::new(vec![
+            b"HTTP/1.1 302 Found\r\nLocation: /next\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\n\r\n",
+        ]);
+        let mut client = Client::with_connector(connector);
+        client.add_interceptor(|req: &mut Outgoing, next: Next| -> ::Result<Response> {
+            if req.url.serialize() == "http://old.domain/" {
+                req.url = Url::parse("http://new.domain/").unwrap();
+            }
+            next.send(req)
+        });
+        let res = client.get("http://old.domain/")
+            .header(Authorization("secret".to_owned()))
+            .send()
+            .unwrap();
+        assert_eq!(res.url, Url::parse("http://new.domain/next").unwrap());
+        assert_eq!(res.history, vec![Url::parse("http://new.domain/").unwrap()]);
+
+        // the redirect stays on the host the request was sent to
+        let req = written(&streams, 1);
+        assert!(req.contains("Host: new.domain\r\n"), "{:?}", req);
+        assert!(req.contains("Authorization: secret\r\n"), "{:?}", req);
+    }
+
+    #[test]
+    fn test_interceptor_short_circuit() {
+        let (connector, streams) = RecordingConnector::new(vec![]);
+        let mut client = Client::with_connector(connector);
+        client.add_interceptor(|req: &mut Outgoing, _: Next| -> ::Result<Response> {
+            Ok(Response::with_body(req.url.clone(), StatusCode::ImATeapot, Headers::new(),
+                                   b"cached".to_vec()))
+        });
+        let mut res = client.get("http://127.0.0.1/").send().unwrap();
+        assert_eq!(res.status, StatusCode::ImATeapot);
+        assert_eq!(res.text().unwrap(), "cached");
+        assert!(streams.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_interceptor_order() {
+        let (connector, _) = RecordingConnector::new(vec![b"HTTP/1.1 200 OK\r\n\r\n"]);
+        let calls = Arc::new(Mutex::new(vec![]));
+        let mut client = Client::with_connector(connector);
+        for name in &["outer", "inner"] {
+            let calls = calls.clone();
+            client.add_interceptor(move |req: &mut Outgoing, next: Next| -> ::Result<Response> {
+                calls.lock().unwrap().push(format!("{} before", name));
+                let mut res = try!(next.send(req));
+                calls.lock().unwrap().push(format!("{} after {}", name, res.status));
+                res.status = StatusCode::Accepted;
+                Ok(res)
+            });
+        }
+        let res = client.get("http://127.0.0.1/").send().unwrap();
+        assert_eq!(res.status, StatusCode::Accepted);
+        assert_eq!(*calls.lock().unwrap(), vec![
+            "outer before",
+            "inner before",
+            "inner after 200 OK",
+            "outer after 202 Accepted",
+        ]);
+    }
+
+    mock_connector!(MockProxyConnector {
+        "http://proxy.local" =>     "HTTP/1.1 200 OK\r\n\
+                                     Server: proxy\r\n\
+                                     \r\n\
+                                    "
+    });
+
+    #[test]
+    fn test_http_proxy() {
+        let mut client = Client::with_connector(MockProxyConnector);
+        client.proxy = Some(Proxy::new("proxy.local", 3128).into());
+        let res = client.get("http://127.0.0.1/path").send().unwrap();
+        assert_eq!(res.headers.get(), Some(&Server("proxy".to_owned())));
+    }
+
+    #[test]
+    fn test_http_no_proxy() {
+        let mut client = Client::with_connector(MockRedirectPolicy);
+        let mut config = ProxyConfig::from(Proxy::new("proxy.local", 3128));
+        config.no_proxy = NoProxy::from("127.0.0.0/8");
+        client.proxy = Some(config);
+        let res = client.get("http://127.0.0.2").send().unwrap();
+        assert_eq!(res.headers.get(), Some(&Server("mock3".to_owned())));
+    }
+
+    mock_connector!(MockCookieConnector {
+        "http://127.0.0.1" =>       "HTTP/1.1 302 Found\r\n\
+                                     Location: http://127.0.0.2\r\n\
+                                     Set-Cookie: session=abc\r\n\
+                                     \r\n\
+                                    "
+        "http://127.0.0.2" =>       "HTTP/1.1 200 OK\r\n\
+                                     Set-Cookie: lang=en; Path=/docs\r\n\
+                                     \r\n\
+                                    "
+    });
+
+    #[test]
+    fn test_cookie_store() {
+        let mut client = Client::with_connector(MockCookieConnector);
+        client.set_cookie_store(CookieStore::new());
+        client.get("http://127.0.0.1").send().unwrap();
+
+        let store = client.cookie_store().unwrap();
+        let cookies = store.matching(&Url::parse("http://127.0.0.1/").unwrap());
+        assert_eq!(cookies.len(), 1);
+        assert_eq!(cookies[0].name, "session");
+        let cookies = store.matching(&Url::parse("http://127.0.0.2/docs").unwrap());
+        assert_eq!(cookies.len(), 1);
+        assert_eq!(cookies[0].name, "lang");
+    }
+
+    #[test]
+    fn test_cookie_store_sends_cookies() {
+        let (connector, streams) = RecordingConnector::new(vec![
+            b"HTTP/1.1 302 Found\r\nLocation: http://127.0.0.2\r\n\
+              Set-Cookie: session=abc\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nSet-Cookie: lang=en; Path=/docs\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\n\r\n",
+        ]);
+        let mut client = Client::with_connector(connector);
+        client.set_cookie_store(CookieStore::new());
+        client.get("http://127.0.0.1").send().unwrap();
+        client.get("http://127.0.0.1/").send().unwrap();
+        client.get("http://127.0.0.2/docs").send().unwrap();
+
+        // the redirect hop goes to another host
+        let req = written(&streams, 1);
+        assert!(req.starts_with("GET / HTTP/1.1\r\nHost: 127.0.0.2\r\n"), "{:?}", req);
+        assert!(!req.contains("Cookie:"), "{:?}", req);
+        let req = written(&streams, 2);
+        assert!(req.contains("Cookie: session=abc\r\n"), "{:?}", req);
+        let req = written(&streams, 3);
+        assert!(req.contains("Cookie: lang=en\r\n"), "{:?}", req);
+        assert!(!req.contains("session=abc"), "{:?}", req);
+    }
+
+    #[test]
+    fn test_no_cookie_store() {
+        let client = Client::with_connector(MockCookieConnector);
+        client.get("http://127.0.0.1").send().unwrap();
+        assert!(client.cookie_store().is_none());
+    }
+
     mock_connector!(Issue640Connector {
         b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\n",
         b"GET",
@@ -520,6 +1665,299 @@ mod tests {
         b"POST"
     });
 
+    /// Connects to each of the responses in turn, where `None` is a
+    /// connection that fails when it is read from.
+    struct FlakyConnector(Mutex<Vec<Option<&'static [u8]>>>);
+
+    impl FlakyConnector {
+        fn new(mut responses: Vec<Option<&'static [u8]>>) -> FlakyConnector {
+            responses.reverse();
+            FlakyConnector(Mutex::new(responses))
+        }
+    }
+
+    impl NetworkConnector for FlakyConnector {
+        type Stream = MockStream;
+
+        fn connect(&self, _: &str, _: u16, _: &str) -> ::Result<MockStream> {
+            match self.0.lock().unwrap().pop() {
+                Some(Some(res)) => Ok(MockStream::with_input(res)),
+                Some(None) => {
+                    let mut stream = MockStream::new();
+                    stream.error_on_read = true;
+                    Ok(stream)
+                },
+                None => panic!("FlakyConnector ran out of responses")
+            }
+        }
+    }
+
+    fn retry_policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: max_retries,
+            backoff: Duration::from_millis(1),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_retry_connection_error() {
+        let mut client = Client::with_connector(FlakyConnector::new(vec![
+            None,
+            None,
+            Some(b"HTTP/1.1 200 OK\r\nServer: retried\r\n\r\n"),
+        ]));
+        client.set_retry_policy(retry_policy(2));
+        let res = client.get("http://127.0.0.1").send().unwrap();
+        assert_eq!(res.headers.get(), Some(&Server("retried".to_owned())));
+    }
+
+    #[test]
+    fn test_retry_exhausted() {
+        let mut client = Client::with_connector(FlakyConnector::new(vec![None, None]));
+        client.set_retry_policy(retry_policy(1));
+        assert!(client.get("http://127.0.0.1").send().is_err());
+    }
+
+    #[test]
+    fn test_no_retry_by_default() {
+        let client = Client::with_connector(FlakyConnector::new(vec![None]));
+        assert!(client.get("http://127.0.0.1").send().is_err());
+    }
+
+    #[test]
+    fn test_no_retry_not_idempotent() {
+        let mut client = Client::with_connector(FlakyConnector::new(vec![None]));
+        client.set_retry_policy(retry_policy(3));
+        assert!(client.post("http://127.0.0.1").body("foo=bar").send().is_err());
+    }
+
+    #[test]
+    fn test_retry_replays_buf_body() {
+        let mut client = Client::with_connector(FlakyConnector::new(vec![
+            None,
+            Some(b"HTTP/1.1 200 OK\r\n\r\n"),
+        ]));
+        client.set_retry_policy(retry_policy(3));
+        client.put("http://127.0.0.1").body("foo=bar").send().unwrap();
+    }
+
+    #[test]
+    fn test_no_retry_stream_body() {
+        let mut client = Client::with_connector(FlakyConnector::new(vec![None]));
+        client.set_retry_policy(retry_policy(3));
+        let mut body = io::Cursor::new(b"foo=bar".to_vec());
+        assert!(client.put("http://127.0.0.1").body(&mut body).send().is_err());
+    }
+
+    struct FailingBody;
+
+    impl Read for FailingBody {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::Other, "body failed"))
+        }
+    }
+
+    #[test]
+    fn test_body_error() {
+        // the request is cut short, so a response to it can't be trusted
+        let client = Client::with_connector(FlakyConnector::new(vec![
+            Some(b"HTTP/1.1 200 OK\r\n\r\n"),
+        ]));
+        match client.put("http://127.0.0.1").body(&mut FailingBody).send() {
+            Err(Error::Io(ref e)) => assert_eq!(e.to_string(), "body failed"),
+            other => panic!("expected Io error, got {:?}", other.map(|res| res.status))
+        }
+    }
+
+    #[test]
+    fn test_retry_unavailable() {
+        let unavailable: &'static [u8] = b"HTTP/1.1 503 Service Unavailable\r\n\
+                                          Retry-After: 0\r\n\
+                                          Content-Length: 0\r\n\
+                                          \r\n";
+        let ok: &'static [u8] = b"HTTP/1.1 200 OK\r\n\r\n";
+
+        let mut client = Client::with_connector(FlakyConnector::new(vec![Some(unavailable), Some(ok)]));
+        let mut policy = retry_policy(1);
+        policy.retry_unavailable = true;
+        client.set_retry_policy(policy);
+        assert_eq!(client.get("http://127.0.0.1").send().unwrap().status, StatusCode::Ok);
+
+        let mut client = Client::with_connector(FlakyConnector::new(vec![Some(unavailable)]));
+        client.set_retry_policy(retry_policy(1));
+        assert_eq!(client.get("http://127.0.0.1").send().unwrap().status,
+                   StatusCode::ServiceUnavailable);
+    }
+
+    #[test]
+    fn test_retry_backoff() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(350),
+            retry_unavailable: false,
+        };
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff(2), Duration::from_millis(350));
+        assert_eq!(policy.backoff(10), Duration::from_millis(350));
+    }
+
+    #[test]
+    fn test_request_timeouts() {
+        let (connector, streams) = RecordingConnector::new(vec![b"HTTP/1.1 200 OK\r\n\r\n"]);
+        let mut client = Client::with_connector(connector);
+        client.set_read_timeout(Some(Duration::from_secs(30)));
+        client.set_write_timeout(Some(Duration::from_secs(30)));
+        client.get("http://127.0.0.1")
+            .read_timeout(Duration::from_secs(5))
+            .send()
+            .unwrap();
+        let streams = streams.lock().unwrap();
+        let stream = streams[0].inner.lock().unwrap();
+        assert_eq!(stream.read_timeout.get(), Some(Duration::from_secs(5)));
+        assert_eq!(stream.write_timeout.get(), Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_deadline_shortens_timeouts() {
+        let (connector, streams) = RecordingConnector::new(vec![b"HTTP/1.1 200 OK\r\n\r\n"]);
+        let client = Client::with_connector(connector);
+        client.get("http://127.0.0.1")
+            .read_timeout(Duration::from_secs(60))
+            .timeout(Duration::from_secs(10))
+            .send()
+            .unwrap();
+        let streams = streams.lock().unwrap();
+        let stream = streams[0].inner.lock().unwrap();
+        assert!(stream.read_timeout.get().unwrap() <= Duration::from_secs(10));
+        assert!(stream.write_timeout.get().unwrap() <= Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_deadline_passed() {
+        let client = Client::with_connector(MockRedirectPolicy);
+        match client.get("http://127.0.0.1").timeout(Duration::from_millis(0)).send() {
+            Err(Error::Timeout) => (),
+            other => panic!("expected Timeout, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_deadline_body_read() {
+        let (connector, _) = RecordingConnector::new(vec![
+            b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nfoo"
+        ]);
+        let client = Client::with_connector(connector);
+        let mut res = client.get("http://127.0.0.1")
+            .timeout(Duration::from_millis(10))
+            .send()
+            .unwrap();
+        thread::sleep(Duration::from_millis(20));
+        let err = res.read_to_end(&mut vec![]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+
+    /// A stream whose reads always time out.
+    struct StalledStream;
+
+    impl Read for StalledStream {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::WouldBlock, "mock timeout"))
+        }
+    }
+
+    impl Write for StalledStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl NetworkStream for StalledStream {
+        fn peer_addr(&mut self) -> io::Result<SocketAddr> {
+            Ok("127.0.0.1:1337".parse().unwrap())
+        }
+    }
+
+    struct StalledConnector;
+
+    impl NetworkConnector for StalledConnector {
+        type Stream = StalledStream;
+
+        fn connect(&self, _: &str, _: u16, _: &str) -> ::Result<StalledStream> {
+            Ok(StalledStream)
+        }
+    }
+
+    #[test]
+    fn test_read_timeout_error() {
+        let client = Client::with_connector(StalledConnector);
+        match client.get("http://127.0.0.1").read_timeout(Duration::from_millis(50)).send() {
+            Err(Error::Timeout) => (),
+            other => panic!("expected Timeout, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_deadline_bounds_body_reads() {
+        let (connector, streams) = RecordingConnector::new(vec![
+            b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nfoo"
+        ]);
+        let client = Client::with_connector(connector);
+        let mut res = client.get("http://127.0.0.1")
+            .read_timeout(Duration::from_secs(60))
+            .timeout(Duration::from_secs(10))
+            .send()
+            .unwrap();
+        let mut body = String::new();
+        res.read_to_string(&mut body).unwrap();
+        assert_eq!(body, "foo");
+        let streams = streams.lock().unwrap();
+        let stream = streams[0].inner.lock().unwrap();
+        assert!(stream.read_timeout.get().unwrap() <= Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_pool_stats_handle() {
+        let (connector, _) = RecordingConnector::new(vec![
+            b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nfoo"
+        ]);
+        let pool = Pool::with_connector(Default::default(), connector);
+        let stats = pool.stats_handle();
+        let client = Client::with_connector(pool);
+
+        let mut res = client.get("http://127.0.0.1").send().unwrap();
+        assert_eq!(stats.clone().stats().hosts["http://127.0.0.1:80"].active, 1);
+        res.read_to_end(&mut vec![]).unwrap();
+        drop(res);
+        let stats = stats.stats();
+        assert_eq!(stats.created, 1);
+        assert_eq!(stats.hosts["http://127.0.0.1:80"].idle, 1);
+    }
+
+    #[test]
+    fn test_connect_timeout_bounds_pool_wait() {
+        let (connector, _) = RecordingConnector::new(vec![
+            b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nfoo"
+        ]);
+        let config = pool::Config {
+            max_per_host: Some(1),
+            ..Default::default()
+        };
+        let client = Client::with_connector(Pool::with_connector(config, connector));
+        let _res = client.get("http://127.0.0.1").send().unwrap();
+        // the only connection is still in use
+        match client.get("http://127.0.0.1").connect_timeout(Duration::from_millis(20)).send() {
+            Err(Error::Timeout) => (),
+            other => panic!("expected Timeout, got {:?}", other)
+        }
+    }
+
     // see issue #640
     #[test]
     fn test_head_response_body_keep_alive() {
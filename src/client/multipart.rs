@@ -0,0 +1,289 @@
+//! Multipart form bodies.
+//!
+//! A `Multipart` is a `multipart/form-data` body, made of text fields and
+//! file parts. It is streamed as it is sent, so files are not read into
+//! memory.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use hyper::Client;
+//! use hyper::client::{Multipart, Part};
+//!
+//! let client = Client::new();
+//! let form = Multipart::new()
+//!     .text("version", "1.0.3")
+//!     .file("artifact", "target/release/app.tar.gz").unwrap()
+//!     .part("notes", Part::bytes(b"fixes #12".to_vec()).file_name("NOTES"));
+//! let res = client.post("http://example.domain/upload").multipart(form).send().unwrap();
+//! ```
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Cursor, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+use mime::{Mime, TopLevel, SubLevel, Attr, Value};
+use time;
+
+use header::ContentType;
+
+static BOUNDARY_COUNTER: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// A `multipart/form-data` body.
+pub struct Multipart {
+    boundary: String,
+    segments: VecDeque<Segment>,
+    size: Option<u64>,
+    closed: bool,
+}
+
+enum Segment {
+    Bytes(Cursor<Vec<u8>>),
+    Reader(Box<Read + Send>),
+}
+
+impl Multipart {
+    /// Create an empty form, with a generated boundary.
+    pub fn new() -> Multipart {
+        let count = BOUNDARY_COUNTER.fetch_add(1, Ordering::Relaxed);
+        Multipart::with_boundary(format!("hyper-boundary-{:016x}{:08x}",
+                                         time::precise_time_ns(), count))
+    }
+
+    /// Create an empty form that separates its parts with the given boundary.
+    ///
+    /// The boundary must not appear in the contents of any part.
+    pub fn with_boundary<B: Into<String>>(boundary: B) -> Multipart {
+        Multipart {
+            boundary: boundary.into(),
+            segments: VecDeque::new(),
+            size: Some(0),
+            closed: false,
+        }
+    }
+
+    /// The boundary between the parts of this form.
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// Add a text field.
+    pub fn text<N, V>(self, name: N, value: V) -> Multipart
+    where N: Into<String>, V: Into<String> {
+        self.part(name, Part::bytes(value.into().into_bytes()))
+    }
+
+    /// Add a file part, read from the file at `path`.
+    ///
+    /// The part is named after the file, and has the content type
+    /// `application/octet-stream`.
+    pub fn file<N, P>(self, name: N, path: P) -> io::Result<Multipart>
+    where N: Into<String>, P: AsRef<Path> {
+        let path = path.as_ref();
+        let file = try!(File::open(path));
+        let len = try!(file.metadata()).len();
+        let mut part = Part::reader(file, Some(len)).mime(octet_stream());
+        if let Some(file_name) = path.file_name() {
+            part = part.file_name(file_name.to_string_lossy().into_owned());
+        }
+        Ok(self.part(name, part))
+    }
+
+    /// Add a part.
+    pub fn part<N: Into<String>>(mut self, name: N, part: Part) -> Multipart {
+        let mut head = format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"",
+                               self.boundary, escape(&name.into()));
+        if let Some(ref file_name) = part.file_name {
+            head.push_str(&format!("; filename=\"{}\"", escape(file_name)));
+        }
+        if let Some(ref mime) = part.mime {
+            head.push_str(&format!("\r\nContent-Type: {}", mime));
+        }
+        head.push_str("\r\n\r\n");
+
+        self.size = match (self.size, part.size) {
+            (Some(size), Some(len)) => Some(size + head.len() as u64 + len + 2),
+            _ => None
+        };
+        self.segments.push_back(Segment::Bytes(Cursor::new(head.into_bytes())));
+        self.segments.push_back(part.body);
+        self.segments.push_back(Segment::Bytes(Cursor::new(b"\r\n".to_vec())));
+        self
+    }
+
+    /// The length of the encoded form, if the size of every part is known.
+    pub fn size(&self) -> Option<u64> {
+        self.size.map(|size| size + self.closing().len() as u64)
+    }
+
+    /// The `Content-Type` of this form, with its boundary.
+    pub fn content_type(&self) -> ContentType {
+        ContentType(Mime(TopLevel::Multipart, SubLevel::FormData,
+                         vec![(Attr::Boundary, Value::Ext(self.boundary.clone()))]))
+    }
+
+    fn closing(&self) -> String {
+        format!("--{}--\r\n", self.boundary)
+    }
+}
+
+impl Read for Multipart {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.closed {
+            let closing = self.closing().into_bytes();
+            self.segments.push_back(Segment::Bytes(Cursor::new(closing)));
+            self.closed = true;
+        }
+        while let Some(mut segment) = self.segments.pop_front() {
+            let n = try!(match segment {
+                Segment::Bytes(ref mut bytes) => bytes.read(buf),
+                Segment::Reader(ref mut reader) => reader.read(buf),
+            });
+            if n > 0 || buf.is_empty() {
+                self.segments.push_front(segment);
+                return Ok(n);
+            }
+        }
+        Ok(0)
+    }
+}
+
+impl fmt::Debug for Multipart {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Multipart")
+            .field("boundary", &self.boundary)
+            .field("size", &self.size())
+            .finish()
+    }
+}
+
+/// A part of a `Multipart` form.
+pub struct Part {
+    body: Segment,
+    size: Option<u64>,
+    file_name: Option<String>,
+    mime: Option<Mime>,
+}
+
+impl Part {
+    /// A part with the given contents.
+    pub fn bytes(bytes: Vec<u8>) -> Part {
+        Part {
+            size: Some(bytes.len() as u64),
+            body: Segment::Bytes(Cursor::new(bytes)),
+            file_name: None,
+            mime: None,
+        }
+    }
+
+    /// A part that is read from a reader, which is streamed as the form is
+    /// sent.
+    ///
+    /// Without a size, the form has no `Content-Length` and is sent chunked.
+    pub fn reader<R: Read + Send + 'static>(reader: R, size: Option<u64>) -> Part {
+        Part {
+            body: Segment::Reader(Box::new(reader)),
+            size: size,
+            file_name: None,
+            mime: None,
+        }
+    }
+
+    /// Set the file name of this part, which makes it a file upload.
+    pub fn file_name<S: Into<String>>(mut self, file_name: S) -> Part {
+        self.file_name = Some(file_name.into());
+        self
+    }
+
+    /// Set the `Content-Type` of this part.
+    pub fn mime(mut self, mime: Mime) -> Part {
+        self.mime = Some(mime);
+        self
+    }
+}
+
+impl fmt::Debug for Part {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Part")
+            .field("size", &self.size)
+            .field("file_name", &self.file_name)
+            .field("mime", &self.mime)
+            .finish()
+    }
+}
+
+fn octet_stream() -> Mime {
+    Mime(TopLevel::Application, SubLevel::Ext("octet-stream".to_owned()), vec![])
+}
+
+/// Escape a name or file name for a quoted `Content-Disposition` parameter,
+/// the way browsers do.
+fn escape(s: &str) -> String {
+    s.replace('"', "%22").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read};
+    use mime::{Mime, TopLevel, SubLevel};
+    use super::{Multipart, Part};
+
+    fn read_all(form: &mut Multipart) -> String {
+        let mut s = String::new();
+        form.read_to_string(&mut s).unwrap();
+        s
+    }
+
+    #[test]
+    fn test_multipart_text() {
+        let mut form = Multipart::with_boundary("XyZ")
+            .text("a", "1")
+            .text("b\"c", "two");
+        let size = form.size();
+        let body = read_all(&mut form);
+        assert_eq!(body, "--XyZ\r\n\
+                          Content-Disposition: form-data; name=\"a\"\r\n\
+                          \r\n\
+                          1\r\n\
+                          --XyZ\r\n\
+                          Content-Disposition: form-data; name=\"b%22c\"\r\n\
+                          \r\n\
+                          two\r\n\
+                          --XyZ--\r\n");
+        assert_eq!(size, Some(body.len() as u64));
+    }
+
+    #[test]
+    fn test_multipart_file_part() {
+        let part = Part::reader(io::Cursor::new(b"data".to_vec()), Some(4))
+            .file_name("a.txt")
+            .mime(Mime(TopLevel::Text, SubLevel::Plain, vec![]));
+        let mut form = Multipart::with_boundary("XyZ").part("upload", part);
+        let size = form.size();
+        let body = read_all(&mut form);
+        assert_eq!(body, "--XyZ\r\n\
+                          Content-Disposition: form-data; name=\"upload\"; filename=\"a.txt\"\r\n\
+                          Content-Type: text/plain\r\n\
+                          \r\n\
+                          data\r\n\
+                          --XyZ--\r\n");
+        assert_eq!(size, Some(body.len() as u64));
+    }
+
+    #[test]
+    fn test_multipart_unsized() {
+        let form = Multipart::new()
+            .text("a", "1")
+            .part("b", Part::reader(io::empty(), None));
+        assert_eq!(form.size(), None);
+    }
+
+    #[test]
+    fn test_multipart_content_type() {
+        let form = Multipart::with_boundary("XyZ");
+        assert_eq!(form.content_type().to_string(), "multipart/form-data; boundary=XyZ");
+        assert!(Multipart::new().boundary() != Multipart::new().boundary());
+    }
+}
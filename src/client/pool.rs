@@ -1,19 +1,22 @@
 //! Client Connection Pooling
 use std::borrow::ToOwned;
-use std::collections::HashMap;
+use std::cmp;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
 use std::io::{self, Read, Write};
+use std::mem;
 use std::net::{SocketAddr, Shutdown};
-use std::sync::{Arc, Mutex};
-
-#[cfg(feature = "timeouts")]
-use std::time::Duration;
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use net::{NetworkConnector, NetworkStream, DefaultConnector};
 
 /// The `NetworkConnector` that behaves as a connection pool used by hyper's `Client`.
 pub struct Pool<C: NetworkConnector> {
     connector: C,
-    inner: Arc<Mutex<PoolImpl<<C as NetworkConnector>::Stream>>>
+    inner: Arc<Mutex<PoolImpl<<C as NetworkConnector>::Stream>>>,
+    available: Arc<Condvar>,
 }
 
 /// Config options for the `Pool`.
@@ -21,6 +24,38 @@ pub struct Pool<C: NetworkConnector> {
 pub struct Config {
     /// The maximum idle connections *per host*.
     pub max_idle: usize,
+    /// The maximum open connections *per host*, idle or in use.
+    ///
+    /// When a host has this many connections open and none is idle,
+    /// `connect` blocks until one is returned to the pool or closed.
+    pub max_per_host: Option<usize>,
+    /// The maximum open connections to all hosts, idle or in use.
+    ///
+    /// When the pool is full, an idle connection to another host is closed
+    /// to make room, and otherwise `connect` blocks as with `max_per_host`.
+    pub max_total: Option<usize>,
+    /// How long `connect` may block waiting for a connection, before it
+    /// fails with `Error::Timeout`.
+    ///
+    /// The connect timeout of a request, as given to `connect_timeout`,
+    /// bounds the wait too. Whichever ends first applies, and a new
+    /// connection is given what is left of the connect timeout.
+    pub checkout_timeout: Option<Duration>,
+    /// How long a connection may sit idle in the pool before it is closed.
+    pub idle_timeout: Option<Duration>,
+    /// How long a connection may be used for, from when it was opened,
+    /// before it is closed.
+    pub max_lifetime: Option<Duration>,
+    /// Whether to check that an idle connection hasn't been closed by the
+    /// server before reusing it.
+    ///
+    /// See `NetworkStream::is_stale`.
+    pub check_liveness: bool,
+    /// How often a background thread closes expired idle connections.
+    ///
+    /// Without it, expired connections are only closed when a connection
+    /// to the same host is checked out, or by `Pool::evict_expired`.
+    pub eviction_interval: Option<Duration>,
 }
 
 impl Default for Config {
@@ -28,14 +63,106 @@ impl Default for Config {
     fn default() -> Config {
         Config {
             max_idle: 5,
+            max_per_host: None,
+            max_total: None,
+            checkout_timeout: None,
+            idle_timeout: None,
+            max_lifetime: None,
+            check_liveness: false,
+            eviction_interval: None,
         }
     }
 }
 
-#[derive(Debug)]
+/// A snapshot of the connections of a `Pool`, and what it has done with
+/// them so far.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// The number of connections opened.
+    pub created: u64,
+    /// The number of times an idle connection was reused.
+    pub reused: u64,
+    /// The number of connections the pool closed because they were expired,
+    /// stale, or didn't fit within its limits.
+    pub evicted: u64,
+    /// The number of connections closed because of an I/O error.
+    pub errored: u64,
+    /// The open connections of each host, keyed by origin, such as
+    /// `http://example.domain:80`.
+    pub hosts: HashMap<String, HostStats>,
+}
+
+/// The open connections to a host.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HostStats {
+    /// Connections waiting in the pool to be reused.
+    pub idle: usize,
+    /// Connections in use, or being opened.
+    pub active: usize,
+}
+
+/// Why a pooled connection was closed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CloseReason {
+    /// It was closed while in use, by either side.
+    Closed,
+    /// An I/O error happened on it.
+    Error,
+    /// It passed the `idle_timeout` or `max_lifetime` of the pool.
+    Expired,
+    /// The server closed it while it was idle.
+    Stale,
+    /// There was no room for it in the pool, or the pool was cleared.
+    Evicted,
+}
+
+/// Callbacks on what a `Pool` does with its connections.
+///
+/// Every method does nothing by default. They are called while the pool is
+/// locked, so they should return quickly, and must not use the pool.
+pub trait PoolListener: Send + Sync {
+    /// A new connection was opened.
+    fn on_connect(&self, _host: &str, _port: u16, _scheme: &str) {}
+
+    /// A connection was handed out, either a new one or a reused idle one.
+    fn on_checkout(&self, _host: &str, _port: u16, _scheme: &str, _reused: bool) {}
+
+    /// A connection was returned to the pool to be reused.
+    fn on_checkin(&self, _host: &str, _port: u16, _scheme: &str) {}
+
+    /// A connection was closed.
+    fn on_drop(&self, _host: &str, _port: u16, _scheme: &str, _reason: CloseReason) {}
+}
+
 struct PoolImpl<S> {
     conns: HashMap<Key, Vec<PooledStreamInner<S>>>,
     config: Config,
+    evicting: bool,
+    stats: Stats,
+    listener: Option<Box<PoolListener>>,
+    // connections open per host, whether idle, in use or being connected
+    open: HashMap<Key, usize>,
+    total: usize,
+    // threads waiting for a connection, served in order
+    waiters: VecDeque<Waiter<S>>,
+    next_waiter: usize,
+    // the time that connections are aged against; the tests replace it
+    clock: Box<Fn() -> Instant + Send>,
+}
+
+#[derive(Debug)]
+struct Waiter<S> {
+    id: usize,
+    key: Key,
+    grant: Option<Grant<S>>,
+}
+
+/// What a waiting thread is handed: an idle connection, or room to open a
+/// new one.
+#[derive(Debug)]
+enum Grant<S> {
+    Idle(PooledStreamInner<S>),
+    Connect,
 }
 
 type Key = (String, u16, Scheme);
@@ -51,6 +178,16 @@ enum Scheme {
     Other(String)
 }
 
+impl Scheme {
+    fn as_str(&self) -> &str {
+        match *self {
+            Scheme::Http => "http",
+            Scheme::Https => "https",
+            Scheme::Other(ref s) => s
+        }
+    }
+}
+
 impl<'a> From<&'a str> for Scheme {
     fn from(s: &'a str) -> Scheme {
         match s {
@@ -78,52 +215,459 @@ impl<C: NetworkConnector> Pool<C> {
             inner: Arc::new(Mutex::new(PoolImpl {
                 conns: HashMap::new(),
                 config: config,
-            }))
+                evicting: false,
+                stats: Stats::default(),
+                listener: None,
+                open: HashMap::new(),
+                total: 0,
+                waiters: VecDeque::new(),
+                next_waiter: 0,
+                clock: Box::new(Instant::now),
+            })),
+            available: Arc::new(Condvar::new()),
         }
     }
 
     /// Clear all idle connections from the Pool, closing them.
     #[inline]
     pub fn clear_idle(&mut self) {
-        self.inner.lock().unwrap().conns.clear();
+        let mut pool = self.inner.lock().unwrap();
+        let conns = mem::replace(&mut pool.conns, HashMap::new());
+        for (key, vec) in conns {
+            for _ in vec {
+                pool.closed(&key, CloseReason::Evicted);
+            }
+        }
+        if pool.dispatch() {
+            self.available.notify_all();
+        }
+    }
+
+    /// Close the idle connections that have passed their `idle_timeout` or
+    /// `max_lifetime`.
+    #[inline]
+    pub fn evict_expired(&self) {
+        let mut pool = self.inner.lock().unwrap();
+        let now = pool.now();
+        pool.evict_expired(now);
+        if pool.dispatch() {
+            self.available.notify_all();
+        }
+    }
+
+    /// Set the listener to call on what the pool does with its
+    /// connections.
+    pub fn set_listener<L: PoolListener + 'static>(&mut self, listener: L) {
+        self.inner.lock().unwrap().listener = Some(Box::new(listener));
+    }
+
+    /// A snapshot of the connections of the pool.
+    pub fn stats(&self) -> Stats {
+        self.inner.lock().unwrap().stats()
+    }
+
+    /// A handle to take snapshots of the connections of the pool, after
+    /// the pool has been given to a `Client`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hyper::Client;
+    /// use hyper::client::Pool;
+    ///
+    /// let pool = Pool::new(Default::default());
+    /// let stats = pool.stats_handle();
+    /// let client = Client::with_connector(pool);
+    /// client.get("http://example.domain").send().unwrap();
+    /// println!("{} connections opened", stats.stats().created);
+    /// ```
+    pub fn stats_handle(&self) -> StatsHandle where C::Stream: Send + 'static {
+        StatsHandle {
+            pool: self.inner.clone(),
+        }
+    }
+}
+
+/// Takes snapshots of the connections of a `Pool`.
+///
+/// Clones of a handle all refer to the same pool.
+#[derive(Clone)]
+pub struct StatsHandle {
+    pool: Arc<StatsSource>,
+}
+
+impl StatsHandle {
+    /// A snapshot of the connections of the pool.
+    pub fn stats(&self) -> Stats {
+        self.pool.stats()
+    }
+}
+
+impl fmt::Debug for StatsHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("StatsHandle")
+    }
+}
+
+/// A pool that stats can be taken from, whatever its stream type.
+trait StatsSource: Send + Sync {
+    fn stats(&self) -> Stats;
+}
+
+impl<S: Send> StatsSource for Mutex<PoolImpl<S>> {
+    fn stats(&self) -> Stats {
+        self.lock().unwrap().stats()
     }
 }
 
 impl<S> PoolImpl<S> {
-    fn reuse(&mut self, key: Key, conn: PooledStreamInner<S>) {
+    #[inline]
+    fn now(&self) -> Instant {
+        (self.clock)()
+    }
+
+    fn stats(&self) -> Stats {
+        let mut stats = self.stats.clone();
+        for (key, &open) in &self.open {
+            let idle = self.conns.get(key).map_or(0, |conns| conns.len());
+            stats.hosts.insert(format!("{}://{}:{}", key.2.as_str(), key.0, key.1), HostStats {
+                idle: idle,
+                active: open - idle,
+            });
+        }
+        stats
+    }
+
+    fn reuse(&mut self, key: Key, mut conn: PooledStreamInner<S>) {
         trace!("reuse {:?}", key);
-        let conns = self.conns.entry(key).or_insert(vec![]);
-        if conns.len() < self.config.max_idle {
-            conns.push(conn);
+        let now = self.now();
+        // a connection that was in use hasn't been idle, so only its
+        // lifetime is up to check
+        if self.is_too_old(&conn, now) {
+            trace!("connection to {:?} expired, closing", key);
+            self.closed(&key, CloseReason::Expired);
+            return;
+        }
+        conn.idle_since = now;
+        let max_idle = self.config.max_idle;
+        let full = {
+            let conns = self.conns.entry(key.clone()).or_insert(vec![]);
+            if conns.len() < max_idle {
+                conns.push(conn);
+                false
+            } else {
+                true
+            }
+        };
+        if full {
+            self.closed(&key, CloseReason::Evicted);
+        } else if let Some(ref listener) = self.listener {
+            listener.on_checkin(&key.0, key.1, key.2.as_str());
+        }
+    }
+
+    fn is_expired(&self, conn: &PooledStreamInner<S>, now: Instant) -> bool {
+        let idle = self.config.idle_timeout.map_or(false, |timeout| {
+            now.duration_since(conn.idle_since) >= timeout
+        });
+        idle || self.is_too_old(conn, now)
+    }
+
+    fn is_too_old(&self, conn: &PooledStreamInner<S>, now: Instant) -> bool {
+        self.config.max_lifetime.map_or(false, |lifetime| {
+            now.duration_since(conn.created) >= lifetime
+        })
+    }
+
+    fn evict_expired(&mut self, now: Instant) {
+        let conns = mem::replace(&mut self.conns, HashMap::new());
+        for (key, vec) in conns {
+            let (expired, vec): (Vec<_>, Vec<_>) = vec.into_iter()
+                .partition(|conn| self.is_expired(conn, now));
+            for _ in expired {
+                self.closed(&key, CloseReason::Expired);
+            }
+            if !vec.is_empty() {
+                self.conns.insert(key, vec);
+            }
+        }
+    }
+
+    fn take_idle(&mut self, key: &Key) -> Option<PooledStreamInner<S>> {
+        let now = self.now();
+        let mut conns = match self.conns.remove(key) {
+            Some(conns) => conns,
+            None => return None
+        };
+        let mut found = None;
+        while let Some(conn) = conns.pop() {
+            if self.is_expired(&conn, now) {
+                trace!("idle connection to {:?} expired, closing", key);
+                self.closed(key, CloseReason::Expired);
+            } else {
+                found = Some(conn);
+                break;
+            }
+        }
+        if !conns.is_empty() {
+            self.conns.insert(key.clone(), conns);
+        }
+        found
+    }
+
+    /// Close the connection that has been idle the longest, to make room
+    /// for another host.
+    fn evict_oldest_idle(&mut self) -> bool {
+        let oldest = self.conns.iter()
+            .filter_map(|(key, conns)| conns.first().map(|conn| (conn.idle_since, key)))
+            .min_by_key(|&(idle_since, _)| idle_since)
+            .map(|(_, key)| key.clone());
+        match oldest {
+            Some(key) => {
+                trace!("closing idle connection to {:?} to make room", key);
+                let empty = {
+                    let conns = self.conns.get_mut(&key).unwrap();
+                    conns.remove(0);
+                    conns.is_empty()
+                };
+                if empty {
+                    self.conns.remove(&key);
+                }
+                self.closed(&key, CloseReason::Evicted);
+                true
+            },
+            None => false
+        }
+    }
+
+    fn opened(&mut self, key: &Key) {
+        *self.open.entry(key.clone()).or_insert(0) += 1;
+        self.total += 1;
+    }
+
+    fn closed(&mut self, key: &Key, reason: CloseReason) {
+        self.release(key);
+        self.dropped(key, reason);
+    }
+
+    /// Record that a connection was closed, without freeing its place.
+    fn dropped(&mut self, key: &Key, reason: CloseReason) {
+        match reason {
+            CloseReason::Closed => (),
+            CloseReason::Error => self.stats.errored += 1,
+            _ => self.stats.evicted += 1
+        }
+        if let Some(ref listener) = self.listener {
+            listener.on_drop(&key.0, key.1, key.2.as_str(), reason);
+        }
+    }
+
+    fn checked_out(&mut self, key: &Key, reused: bool) {
+        if reused {
+            self.stats.reused += 1;
+        } else {
+            self.stats.created += 1;
+        }
+        if let Some(ref listener) = self.listener {
+            if !reused {
+                listener.on_connect(&key.0, key.1, key.2.as_str());
+            }
+            listener.on_checkout(&key.0, key.1, key.2.as_str(), reused);
+        }
+    }
+
+    /// Free the place of a connection, or of one that failed to open.
+    fn release(&mut self, key: &Key) {
+        let gone = match self.open.get_mut(key) {
+            Some(count) => {
+                *count -= 1;
+                self.total -= 1;
+                *count == 0
+            },
+            None => false
+        };
+        if gone {
+            self.open.remove(key);
+        }
+    }
+
+    fn acquire(&mut self, key: &Key) -> Option<Grant<S>> {
+        if let Some(conn) = self.take_idle(key) {
+            return Some(Grant::Idle(conn));
+        }
+        let open = self.open.get(key).cloned().unwrap_or(0);
+        if self.config.max_per_host.map_or(false, |max| open >= max) {
+            return None;
+        }
+        if self.config.max_total.map_or(false, |max| self.total >= max) &&
+                !self.evict_oldest_idle() {
+            return None;
+        }
+        self.opened(key);
+        Some(Grant::Connect)
+    }
+
+    /// Hand out what connections are available to the waiting threads, in
+    /// the order they started waiting. Returns whether any thread was
+    /// served, and so should be woken up.
+    fn dispatch(&mut self) -> bool {
+        let mut waiters = mem::replace(&mut self.waiters, VecDeque::new());
+        let mut served = false;
+        for waiter in waiters.iter_mut().filter(|waiter| waiter.grant.is_none()) {
+            waiter.grant = self.acquire(&waiter.key);
+            served |= waiter.grant.is_some();
+        }
+        self.waiters = waiters;
+        served
+    }
+
+    fn take_grant(&mut self, id: usize) -> Option<Grant<S>> {
+        let pos = self.waiters.iter()
+            .position(|waiter| waiter.id == id && waiter.grant.is_some());
+        pos.and_then(|pos| self.waiters.remove(pos)).and_then(|waiter| waiter.grant)
+    }
+}
+
+fn evict_loop<S>(pool: Weak<Mutex<PoolImpl<S>>>, available: Arc<Condvar>, interval: Duration) {
+    loop {
+        thread::sleep(interval);
+        if !evict_once(&pool, &available) {
+            return;
         }
     }
 }
 
+/// One pass of the eviction thread. Returns `false` once the pool is gone.
+fn evict_once<S>(pool: &Weak<Mutex<PoolImpl<S>>>, available: &Condvar) -> bool {
+    match pool.upgrade() {
+        Some(pool) => match pool.lock() {
+            Ok(mut pool) => {
+                let now = pool.now();
+                pool.evict_expired(now);
+                if pool.dispatch() {
+                    available.notify_all();
+                }
+                true
+            },
+            Err(_) => false
+        },
+        None => false
+    }
+}
+
 impl<C: NetworkConnector<Stream=S>, S: NetworkStream + Send> NetworkConnector for Pool<C> {
     type Stream = PooledStream<S>;
     fn connect(&self, host: &str, port: u16, scheme: &str) -> ::Result<PooledStream<S>> {
+        self.connect_timeout(host, port, scheme, None)
+    }
+
+    /// Check out a connection, giving up after `timeout` has passed.
+    ///
+    /// `timeout` covers both waiting for a connection when the pool is
+    /// full, and connecting. The wait ends at the earlier of `timeout` and
+    /// the `checkout_timeout`, and a new connection is given what is left
+    /// of `timeout`.
+    fn connect_timeout(&self, host: &str, port: u16, scheme: &str,
+                       timeout: Option<Duration>) -> ::Result<PooledStream<S>> {
+        let start = Instant::now();
         let key = key(host, port, scheme);
-        let mut locked = self.inner.lock().unwrap();
-        let mut should_remove = false;
-        let inner = match locked.conns.get_mut(&key) {
-            Some(ref mut vec) => {
-                trace!("Pool had connection, using");
-                should_remove = vec.len() == 1;
-                vec.pop().unwrap()
+        let (grant, check_liveness) = {
+            let mut locked = self.inner.lock().unwrap();
+            if let (false, Some(interval)) = (locked.evicting, locked.config.eviction_interval) {
+                let pool = Arc::downgrade(&self.inner);
+                let available = self.available.clone();
+                thread::spawn(move || evict_loop(pool, available, interval));
+                locked.evicting = true;
             }
-            _ => PooledStreamInner {
+            let wait = match (locked.config.checkout_timeout, timeout) {
+                (Some(checkout), Some(timeout)) => Some(cmp::min(checkout, timeout)),
+                (checkout, timeout) => checkout.or(timeout)
+            };
+            let deadline = wait.map(|wait| start + wait);
+            let id = locked.next_waiter;
+            locked.next_waiter = id.wrapping_add(1);
+            locked.waiters.push_back(Waiter {
+                id: id,
                 key: key.clone(),
-                stream: try!(self.connector.connect(host, port, scheme)),
-                previous_response_expected_no_content: false,
+                grant: None,
+            });
+            if locked.dispatch() {
+                self.available.notify_all();
             }
+            let grant = loop {
+                if let Some(grant) = locked.take_grant(id) {
+                    break grant;
+                }
+                trace!("Pool is full, waiting for a connection to {:?}", key);
+                let now = Instant::now();
+                locked = match deadline {
+                    Some(deadline) if now >= deadline => {
+                        locked.waiters.retain(|waiter| waiter.id != id);
+                        debug!("timed out waiting for a connection to {:?}", key);
+                        return Err(::Error::Timeout);
+                    },
+                    Some(deadline) => self.available.wait_timeout(locked, deadline - now).unwrap().0,
+                    None => self.available.wait(locked).unwrap()
+                };
+            };
+            (grant, locked.config.check_liveness)
         };
-        if should_remove {
-            locked.conns.remove(&key);
-        }
+
+        let inner = match grant {
+            Grant::Idle(mut inner) => {
+                if check_liveness && inner.stream.is_stale() {
+                    // the stale connection's place goes to a new one
+                    trace!("idle connection to {:?} is stale, closing", key);
+                    self.inner.lock().unwrap().dropped(&key, CloseReason::Stale);
+                    None
+                } else {
+                    trace!("Pool had connection, using");
+                    Some(inner)
+                }
+            },
+            Grant::Connect => None
+        };
+        let reused = inner.is_some();
+        let inner = match inner {
+            Some(inner) => inner,
+            None => {
+                let remaining = timeout.map(|timeout| {
+                    timeout.checked_sub(start.elapsed()).unwrap_or(Duration::from_secs(0))
+                });
+                let connected = match remaining {
+                    // the time ran out while waiting for a turn to connect
+                    Some(remaining) if remaining == Duration::from_secs(0) => Err(::Error::Timeout),
+                    _ => self.connector.connect_timeout(host, port, scheme, remaining)
+                };
+                match connected {
+                    Ok(stream) => {
+                        let now = self.inner.lock().unwrap().now();
+                        PooledStreamInner {
+                            key: key.clone(),
+                            stream: stream,
+                            previous_response_expected_no_content: false,
+                            created: now,
+                            idle_since: now,
+                        }
+                    },
+                    Err(e) => {
+                        let mut locked = self.inner.lock().unwrap();
+                        locked.release(&key);
+                        if locked.dispatch() {
+                            self.available.notify_all();
+                        }
+                        return Err(e);
+                    }
+                }
+            }
+        };
+        self.inner.lock().unwrap().checked_out(&key, reused);
         Ok(PooledStream {
             inner: Some(inner),
             is_closed: false,
+            is_errored: false,
             pool: self.inner.clone(),
+            available: self.available.clone(),
         })
     }
 }
@@ -132,7 +676,9 @@ impl<C: NetworkConnector<Stream=S>, S: NetworkStream + Send> NetworkConnector fo
 pub struct PooledStream<S> {
     inner: Option<PooledStreamInner<S>>,
     is_closed: bool,
+    is_errored: bool,
     pool: Arc<Mutex<PoolImpl<S>>>,
+    available: Arc<Condvar>,
 }
 
 #[derive(Debug)]
@@ -140,6 +686,8 @@ struct PooledStreamInner<S> {
     key: Key,
     stream: S,
     previous_response_expected_no_content: bool,
+    created: Instant,
+    idle_since: Instant,
 }
 
 impl<S: NetworkStream> Read for PooledStream<S> {
@@ -153,6 +701,11 @@ impl<S: NetworkStream> Read for PooledStream<S> {
                 self.is_closed = true;
                 Ok(0)
             },
+            Err(e) => {
+                // the stream is in an unknown state, so it isn't reused
+                self.is_errored = true;
+                Err(e)
+            },
             r => r
         }
     }
@@ -161,12 +714,16 @@ impl<S: NetworkStream> Read for PooledStream<S> {
 impl<S: NetworkStream> Write for PooledStream<S> {
     #[inline]
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.inner.as_mut().unwrap().stream.write(buf)
+        let res = self.inner.as_mut().unwrap().stream.write(buf);
+        self.is_errored |= res.is_err();
+        res
     }
 
     #[inline]
     fn flush(&mut self) -> io::Result<()> {
-        self.inner.as_mut().unwrap().stream.flush()
+        let res = self.inner.as_mut().unwrap().stream.flush();
+        self.is_errored |= res.is_err();
+        res
     }
 }
 
@@ -176,13 +733,11 @@ impl<S: NetworkStream> NetworkStream for PooledStream<S> {
         self.inner.as_mut().unwrap().stream.peer_addr()
     }
 
-    #[cfg(feature = "timeouts")]
     #[inline]
     fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
         self.inner.as_ref().unwrap().stream.set_read_timeout(dur)
     }
 
-    #[cfg(feature = "timeouts")]
     #[inline]
     fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
         self.inner.as_ref().unwrap().stream.set_write_timeout(dur)
@@ -194,6 +749,11 @@ impl<S: NetworkStream> NetworkStream for PooledStream<S> {
         self.inner.as_mut().unwrap().stream.close(how)
     }
 
+    #[inline]
+    fn is_stale(&mut self) -> bool {
+        self.inner.as_mut().unwrap().stream.is_stale()
+    }
+
     #[inline]
     fn set_previous_response_expected_no_content(&mut self, expected: bool) {
         trace!("set_previous_response_expected_no_content {}", expected);
@@ -210,33 +770,63 @@ impl<S: NetworkStream> NetworkStream for PooledStream<S> {
 
 impl<S> Drop for PooledStream<S> {
     fn drop(&mut self) {
-        trace!("PooledStream.drop, is_closed={}", self.is_closed);
-        if !self.is_closed {
-            self.inner.take().map(|inner| {
-                if let Ok(mut pool) = self.pool.lock() {
+        trace!("PooledStream.drop, is_closed={}, is_errored={}", self.is_closed, self.is_errored);
+        if let Some(inner) = self.inner.take() {
+            if let Ok(mut pool) = self.pool.lock() {
+                if self.is_errored {
+                    pool.closed(&inner.key, CloseReason::Error);
+                } else if self.is_closed {
+                    pool.closed(&inner.key, CloseReason::Closed);
+                } else {
                     pool.reuse(inner.key.clone(), inner);
                 }
-                // else poisoned, give up
-            });
+                if pool.dispatch() {
+                    self.available.notify_all();
+                }
+            }
+            // else poisoned, give up
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::net::Shutdown;
-    use std::io::Read;
-    use mock::{MockConnector};
-    use net::{NetworkConnector, NetworkStream};
+    use std::net::{Shutdown, TcpListener};
+    use std::io::{Read, Write};
+    use std::sync::{Arc, Condvar, Mutex};
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use mock::{MockConnector, MockStream};
+    use net::{HttpConnector, NetworkConnector, NetworkStream};
 
-    use super::{Pool, key};
+    use super::{CloseReason, Config, HostStats, Pool, PoolListener, evict_once, key};
 
     macro_rules! mocked {
         () => ({
             Pool::with_connector(Default::default(), MockConnector)
+        });
+        ($config:expr) => ({
+            Pool::with_connector($config, MockConnector)
         })
     }
 
+    fn idle_count<C: NetworkConnector>(pool: &Pool<C>) -> usize {
+        pool.inner.lock().unwrap().conns.values().map(|conns| conns.len()).sum()
+    }
+
+    /// Stop the pool's clock, so a test can move it forward by hand.
+    fn mock_clock<C: NetworkConnector>(pool: &Pool<C>) -> Arc<Mutex<Instant>> {
+        let now = Arc::new(Mutex::new(Instant::now()));
+        let clock = now.clone();
+        pool.inner.lock().unwrap().clock = Box::new(move || *clock.lock().unwrap());
+        now
+    }
+
+    fn advance(clock: &Mutex<Instant>, millis: u64) {
+        *clock.lock().unwrap() += Duration::from_millis(millis);
+    }
+
     #[test]
     fn test_connect_and_drop() {
         let pool = mocked!();
@@ -275,4 +865,304 @@ mod tests {
         let locked = pool.inner.lock().unwrap();
         assert_eq!(locked.conns.len(), 0);
     }
+
+    #[test]
+    fn test_idle_timeout() {
+        let pool = mocked!(Config {
+            idle_timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        });
+        let clock = mock_clock(&pool);
+        pool.connect("127.0.0.1", 3000, "http").unwrap();
+        pool.connect("127.0.0.1", 3000, "http").unwrap(); //reused
+        assert_eq!(idle_count(&pool), 1);
+
+        advance(&clock, 49);
+        pool.evict_expired();
+        assert_eq!(idle_count(&pool), 1);
+        advance(&clock, 1);
+        pool.evict_expired();
+        assert_eq!(idle_count(&pool), 0);
+
+        pool.connect("127.0.0.1", 3000, "http").unwrap();
+        advance(&clock, 100);
+        // evicted at checkout, and replaced by a new connection
+        let stream = pool.connect("127.0.0.1", 3000, "http").unwrap();
+        assert_eq!(idle_count(&pool), 0);
+        drop(stream);
+        assert_eq!(idle_count(&pool), 1);
+    }
+
+    #[test]
+    fn test_idle_timeout_long_checkout() {
+        let pool = mocked!(Config {
+            idle_timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        });
+        let clock = mock_clock(&pool);
+        let stream = pool.connect("127.0.0.1", 3000, "http").unwrap();
+        // in use for longer than the idle timeout, but never idle
+        advance(&clock, 100);
+        drop(stream);
+        assert_eq!(pool.stats().hosts["http://127.0.0.1:3000"].idle, 1);
+    }
+
+    #[test]
+    fn test_max_lifetime() {
+        let pool = mocked!(Config {
+            max_lifetime: Some(Duration::from_millis(50)),
+            ..Default::default()
+        });
+        let clock = mock_clock(&pool);
+        let stream = pool.connect("127.0.0.1", 3000, "http").unwrap();
+        advance(&clock, 100);
+        // too old to go back into the pool, however recently it was used
+        drop(stream);
+        assert_eq!(idle_count(&pool), 0);
+    }
+
+    #[test]
+    fn test_background_eviction() {
+        let pool = mocked!(Config {
+            idle_timeout: Some(Duration::from_millis(20)),
+            eviction_interval: Some(Duration::from_secs(3600)),
+            ..Default::default()
+        });
+        let clock = mock_clock(&pool);
+        pool.connect("127.0.0.1", 3000, "http").unwrap();
+        assert!(pool.inner.lock().unwrap().evicting);
+        assert_eq!(idle_count(&pool), 1);
+
+        // drive the thread's passes by hand, rather than waiting on it
+        let weak = Arc::downgrade(&pool.inner);
+        assert!(evict_once(&weak, &pool.available));
+        assert_eq!(idle_count(&pool), 1);
+        advance(&clock, 20);
+        assert!(evict_once(&weak, &pool.available));
+        assert_eq!(idle_count(&pool), 0);
+
+        // and the thread stops once the pool is dropped
+        drop(pool);
+        assert!(!evict_once(&weak, &Condvar::new()));
+    }
+
+    #[test]
+    fn test_check_liveness() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let pool = Pool::with_connector(Config {
+            check_liveness: true,
+            ..Default::default()
+        }, HttpConnector::new());
+
+        pool.connect("127.0.0.1", port, "http").unwrap();
+        let (server, _) = listener.accept().unwrap();
+        drop(server);
+        {
+            // blocks until the close has reached the idle connection
+            let locked = pool.inner.lock().unwrap();
+            locked.conns.values().next().unwrap()[0].stream.0.peek(&mut [0]).unwrap();
+        }
+
+        // the closed connection is skipped, so a new one is opened
+        pool.connect("127.0.0.1", port, "http").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        assert!(listener.accept().is_ok());
+    }
+
+    fn waiting<C: NetworkConnector>(pool: &Pool<C>, count: usize) {
+        while pool.inner.lock().unwrap().waiters.len() < count {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_max_per_host_waits_for_checkin() {
+        let pool = Arc::new(mocked!(Config {
+            max_per_host: Some(1),
+            ..Default::default()
+        }));
+        let stream = pool.connect("127.0.0.1", 3000, "http").unwrap();
+        // other hosts aren't limited
+        pool.connect("127.0.0.1", 3001, "http").unwrap();
+
+        let waiter = {
+            let pool = pool.clone();
+            thread::spawn(move || {
+                pool.connect("127.0.0.1", 3000, "http").unwrap();
+            })
+        };
+        waiting(&pool, 1);
+        drop(stream);
+        waiter.join().unwrap();
+
+        let locked = pool.inner.lock().unwrap();
+        assert_eq!(locked.open.get(&key("127.0.0.1", 3000, "http")), Some(&1));
+        assert_eq!(locked.total, 2);
+    }
+
+    #[test]
+    fn test_checkout_timeout() {
+        let pool = mocked!(Config {
+            max_per_host: Some(1),
+            checkout_timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        });
+        let mut stream = pool.connect("127.0.0.1", 3000, "http").unwrap();
+        match pool.connect("127.0.0.1", 3000, "http") {
+            Err(::Error::Timeout) => (),
+            other => panic!("expected timeout, got {:?}", other.map(|_| ()))
+        }
+        assert!(pool.inner.lock().unwrap().waiters.is_empty());
+
+        // closing the connection makes room for a new one
+        stream.close(Shutdown::Both).unwrap();
+        drop(stream);
+        pool.connect("127.0.0.1", 3000, "http").unwrap();
+    }
+
+    #[test]
+    fn test_request_timeout_bounds_checkout() {
+        let pool = mocked!(Config {
+            max_per_host: Some(1),
+            ..Default::default()
+        });
+        let _stream = pool.connect("127.0.0.1", 3000, "http").unwrap();
+        let timeout = Some(Duration::from_millis(50));
+        match pool.connect_timeout("127.0.0.1", 3000, "http", timeout) {
+            Err(::Error::Timeout) => (),
+            other => panic!("expected timeout, got {:?}", other.map(|_| ()))
+        }
+        assert!(pool.inner.lock().unwrap().waiters.is_empty());
+    }
+
+    #[test]
+    fn test_max_total_closes_other_idle() {
+        let pool = mocked!(Config {
+            max_total: Some(1),
+            checkout_timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        });
+        pool.connect("127.0.0.1", 3000, "http").unwrap();
+        let stream = pool.connect("127.0.0.1", 3001, "http").unwrap();
+        {
+            let locked = pool.inner.lock().unwrap();
+            assert_eq!(locked.conns.len(), 0);
+            assert_eq!(locked.total, 1);
+        }
+        // nothing idle to close while the stream is in use
+        assert!(pool.connect("127.0.0.1", 3000, "http").is_err());
+        drop(stream);
+        assert_eq!(idle_count(&pool), 1);
+    }
+
+    #[test]
+    fn test_waiters_served_in_order() {
+        let pool = Arc::new(mocked!(Config {
+            max_per_host: Some(1),
+            ..Default::default()
+        }));
+        let stream = pool.connect("127.0.0.1", 3000, "http").unwrap();
+        let (tx, rx) = mpsc::channel();
+        let waiters = (0..3).map(|i| {
+            let shared = pool.clone();
+            let tx = tx.clone();
+            let waiter = thread::spawn(move || {
+                let stream = shared.connect("127.0.0.1", 3000, "http").unwrap();
+                tx.send(i).unwrap();
+                thread::sleep(Duration::from_millis(10));
+                drop(stream);
+            });
+            waiting(&pool, i + 1);
+            waiter
+        }).collect::<Vec<_>>();
+
+        drop(stream);
+        for waiter in waiters {
+            waiter.join().unwrap();
+        }
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![0, 1, 2]);
+    }
+
+    struct Events(Arc<Mutex<Vec<String>>>);
+
+    impl PoolListener for Events {
+        fn on_connect(&self, host: &str, port: u16, scheme: &str) {
+            self.0.lock().unwrap().push(format!("connect {}://{}:{}", scheme, host, port));
+        }
+
+        fn on_checkout(&self, _host: &str, _port: u16, _scheme: &str, reused: bool) {
+            self.0.lock().unwrap().push(format!("checkout reused={}", reused));
+        }
+
+        fn on_checkin(&self, _host: &str, _port: u16, _scheme: &str) {
+            self.0.lock().unwrap().push("checkin".to_owned());
+        }
+
+        fn on_drop(&self, _host: &str, _port: u16, _scheme: &str, reason: CloseReason) {
+            self.0.lock().unwrap().push(format!("drop {:?}", reason));
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut pool = mocked!(Config {
+            max_idle: 1,
+            ..Default::default()
+        });
+        let events = Arc::new(Mutex::new(vec![]));
+        pool.set_listener(Events(events.clone()));
+
+        let first = pool.connect("127.0.0.1", 3000, "http").unwrap();
+        let second = pool.connect("127.0.0.1", 3000, "http").unwrap();
+        let stats = pool.stats();
+        assert_eq!(stats.created, 2);
+        assert_eq!(stats.hosts["http://127.0.0.1:3000"], HostStats { idle: 0, active: 2 });
+
+        drop(first);
+        drop(second); // only one can be idle
+        let stats = pool.stats();
+        assert_eq!(stats.evicted, 1);
+        assert_eq!(stats.hosts["http://127.0.0.1:3000"], HostStats { idle: 1, active: 0 });
+
+        let mut stream = pool.connect("127.0.0.1", 3000, "http").unwrap();
+        stream.close(Shutdown::Both).unwrap();
+        drop(stream);
+        let stats = pool.stats();
+        assert_eq!(stats.reused, 1);
+        assert!(stats.hosts.is_empty());
+
+        assert_eq!(*events.lock().unwrap(), vec![
+            "connect http://127.0.0.1:3000",
+            "checkout reused=false",
+            "connect http://127.0.0.1:3000",
+            "checkout reused=false",
+            "checkin",
+            "drop Evicted",
+            "checkout reused=true",
+            "drop Closed",
+        ]);
+    }
+
+    struct BrokenConnector;
+
+    impl NetworkConnector for BrokenConnector {
+        type Stream = MockStream;
+        fn connect(&self, _: &str, _: u16, _: &str) -> ::Result<MockStream> {
+            let mut stream = MockStream::new();
+            stream.error_on_write = true;
+            Ok(stream)
+        }
+    }
+
+    #[test]
+    fn test_error_closes() {
+        let pool = Pool::with_connector(Default::default(), BrokenConnector);
+        let mut stream = pool.connect("127.0.0.1", 3000, "http").unwrap();
+        assert!(stream.write(b"foo").is_err());
+        drop(stream);
+        let stats = pool.stats();
+        assert_eq!(stats.errored, 1);
+        assert!(stats.hosts.is_empty());
+    }
 }
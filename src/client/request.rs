@@ -1,8 +1,6 @@
 //! Client Requests
 use std::marker::PhantomData;
 use std::io::{self, Write};
-
-#[cfg(feature = "timeouts")]
 use std::time::Duration;
 
 use url::Url;
@@ -44,14 +42,12 @@ impl<W> Request<W> {
     pub fn method(&self) -> method::Method { self.method.clone() }
 
     /// Set the write timeout.
-    #[cfg(feature = "timeouts")]
     #[inline]
     pub fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
         self.message.set_write_timeout(dur)
     }
 
     /// Set the read timeout.
-    #[cfg(feature = "timeouts")]
     #[inline]
     pub fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
         self.message.set_read_timeout(dur)
@@ -173,6 +169,7 @@ mod tests {
     use url::form_urlencoded;
     use super::Request;
     use http::h1::Http11Message;
+    use http::HttpMessage;
 
     fn run_request(req: Request<Fresh>) -> Vec<u8> {
         let req = req.start().unwrap();
@@ -220,6 +217,18 @@ mod tests {
         assert!(s.contains("?q=value"));
     }
 
+    #[test]
+    fn test_proxied_absolute_form() {
+        let url = Url::parse("http://example.dom/path?q=value#frag").unwrap();
+        let mut req = Request::with_connector(
+            Get, url, &mut MockConnector
+        ).unwrap();
+        req.message.set_proxied(true);
+        let bytes = run_request(req);
+        let s = from_utf8(&bytes[..]).unwrap();
+        assert!(s.starts_with("GET http://example.dom/path?q=value HTTP/1.1\r\n"));
+    }
+
     #[test]
     fn test_post_content_length() {
         let url = Url::parse("http://example.dom").unwrap();
@@ -1,9 +1,17 @@
 //! Client Responses
-use std::io::{self, Read};
+use std::borrow::Cow;
+use std::fmt;
+use std::io::{self, Cursor, Read};
+use std::mem;
+use std::time::{Duration, Instant};
 
+use flate2::read::{GzDecoder, ZlibDecoder};
+use mime::{Attr, Mime, SubLevel, TopLevel};
+use serialize::Decodable;
+use serialize::json;
 use url::Url;
 
-use header;
+use header::{self, Charset, ContentEncoding, ContentLength, ContentType, Encoding};
 use net::NetworkStream;
 use http::{self, RawStatus, ResponseHead, HttpMessage};
 use http::h1::Http11Message;
@@ -21,8 +29,11 @@ pub struct Response {
     pub version: version::HttpVersion,
     /// The final URL of this response.
     pub url: Url,
+    /// The URLs that redirected to this response, in the order they were
+    /// requested. It is empty when no redirect was followed.
+    pub history: Vec<Url>,
     status_raw: RawStatus,
-    message: Box<HttpMessage>,
+    body: Body,
 }
 
 impl Response {
@@ -47,29 +58,176 @@ impl Response {
         debug!("version={:?}, status={:?}", version, status);
         debug!("headers={:?}", headers);
 
+        let keep_alive = http::should_keep_alive(version, &headers);
         Ok(Response {
             status: status,
             version: version,
             headers: headers,
             url: url,
+            history: vec![],
             status_raw: raw_status,
-            message: message,
+            body: Body::Plain(MessageReader {
+                message: message,
+                keep_alive: keep_alive,
+                deadline: None,
+                read_timeout: None,
+            }),
         })
     }
 
+    /// Creates a response that wasn't received from a server, such as one
+    /// an `Interceptor` answers a request with.
+    pub fn with_body(url: Url, status: status::StatusCode, headers: header::Headers,
+                     body: Vec<u8>) -> Response {
+        let reason = status.canonical_reason().unwrap_or("");
+        Response {
+            status_raw: RawStatus(status.to_u16(), Cow::Borrowed(reason)),
+            status: status,
+            version: version::HttpVersion::Http11,
+            headers: headers,
+            url: url,
+            history: vec![],
+            body: Body::Bytes(Cursor::new(body)),
+        }
+    }
+
     /// Get the raw status code and reason.
     #[inline]
     pub fn status_raw(&self) -> &RawStatus {
         &self.status_raw
     }
+
+    /// Fail reads of the body once `deadline` has passed, with an
+    /// `io::Error` of kind `TimedOut`. Until then, each read waits for at
+    /// most `read_timeout`.
+    #[doc(hidden)]
+    pub fn set_deadline(&mut self, deadline: Instant, read_timeout: Option<Duration>) {
+        let rdr = match self.body {
+            Body::Plain(ref mut rdr) | Body::GzipHeader(ref mut rdr) => rdr,
+            Body::Gzip(ref mut decoder) => decoder.get_mut(),
+            Body::Deflate(ref mut decoder) => decoder.get_mut(),
+            Body::Bytes(_) | Body::Invalid => return
+        };
+        rdr.deadline = Some(deadline);
+        rdr.read_timeout = read_timeout;
+    }
+
+    /// Decode the body of this response, if it has a `gzip` or `deflate`
+    /// `Content-Encoding`.
+    ///
+    /// When the body will be decoded, the `Content-Encoding` and
+    /// `Content-Length` headers are removed, since they describe the encoded
+    /// body. This must be called before any of the body has been read.
+    ///
+    /// Returns whether the body will be decoded.
+    pub fn decode_content(&mut self) -> bool {
+        let encoding = match self.headers.get::<ContentEncoding>() {
+            Some(&ContentEncoding(ref encodings)) if encodings.len() == 1 => {
+                encodings[0].clone()
+            },
+            _ => return false
+        };
+        let body = mem::replace(&mut self.body, Body::Invalid);
+        self.body = match (encoding, body) {
+            (Encoding::Gzip, Body::Plain(rdr)) => Body::GzipHeader(rdr),
+            (Encoding::Deflate, Body::Plain(rdr)) => Body::Deflate(ZlibDecoder::new(rdr)),
+            (_, body) => {
+                self.body = body;
+                return false;
+            }
+        };
+        debug!("decoding {:?} body", self.body);
+        self.headers.remove::<ContentEncoding>();
+        self.headers.remove::<ContentLength>();
+        true
+    }
+
+    /// Read the body as text, decoded in the `charset` of its `Content-Type`.
+    ///
+    /// Without a charset, the body is decoded as UTF-8. Returns
+    /// `Error::Charset` if the charset isn't supported, or the body isn't
+    /// valid in it. See `Charset::decode` for the supported charsets.
+    pub fn text(&mut self) -> ::Result<String> {
+        let charset = match self.headers.get::<ContentType>() {
+            Some(&ContentType(ref mime)) => mime.get_param(Attr::Charset).and_then(|charset| {
+                charset.to_string().parse().ok()
+            }),
+            None => None
+        };
+        let charset = charset.unwrap_or_else(|| Charset::Ext("UTF-8".to_owned()));
+        let mut body = Vec::new();
+        try!(self.read_to_end(&mut body));
+        let text = try!(charset.decode(&body));
+        // a byte order mark isn't part of the text
+        if text.starts_with('\u{feff}') {
+            Ok(text['\u{feff}'.len_utf8()..].to_owned())
+        } else {
+            Ok(text)
+        }
+    }
+
+    /// Read the body as JSON, and decode it into a `T`.
+    ///
+    /// Returns `Error::ContentType` if the response is not `application/json`,
+    /// or another `+json` type. The body is read as `text`.
+    pub fn json<T: Decodable>(&mut self) -> ::Result<T> {
+        match self.headers.get::<ContentType>() {
+            Some(&ContentType(ref mime)) if is_json(mime) => (),
+            other => return Err(::Error::ContentType(other.map(|header| header.0.clone())))
+        }
+        let text = try!(self.text());
+        Ok(try!(json::decode(&text)))
+    }
+}
+
+fn is_json(mime: &Mime) -> bool {
+    match *mime {
+        Mime(TopLevel::Application, SubLevel::Json, _) => true,
+        Mime(TopLevel::Application, SubLevel::Ext(ref sub), _) => sub.ends_with("+json"),
+        _ => false
+    }
 }
 
 impl Read for Response {
     #[inline]
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        match self.message.read(buf) {
+        // an empty read says nothing about the end of the body
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if let Body::GzipHeader(..) = self.body {
+            // the gzip header is read by the decoder when it's created,
+            // so that waits until the body is first read
+            self.body = match mem::replace(&mut self.body, Body::Invalid) {
+                // an empty body has no gzip header to read
+                Body::GzipHeader(rdr) if !rdr.message.has_body() => Body::Plain(rdr),
+                Body::GzipHeader(rdr) => Body::Gzip(try!(GzDecoder::new(rdr))),
+                body => body
+            };
+        }
+        let res = match self.body {
+            Body::Plain(ref mut rdr) => rdr.read(buf),
+            Body::GzipHeader(ref mut rdr) => rdr.read(buf),
+            Body::Gzip(ref mut decoder) => decoder.read(buf),
+            Body::Deflate(ref mut decoder) => decoder.read(buf),
+            Body::Bytes(ref mut bytes) => bytes.read(buf),
+            Body::Invalid => Err(io::Error::new(io::ErrorKind::InvalidData,
+                                                "Invalid gzip body")),
+        };
+        let res = match res {
+            // a decoder may stop before the end of the body, such as
+            // before the last chunk, which would keep the connection from
+            // being reused
+            Ok(0) => match self.body {
+                Body::Gzip(ref mut decoder) => drain(&mut decoder.get_mut().message),
+                Body::Deflate(ref mut decoder) => drain(&mut decoder.get_mut().message),
+                _ => Ok(0)
+            },
+            r => r
+        };
+        match res {
             Err(e) => {
-                let _ = self.message.close_connection();
+                self.body.message().map(|message| message.close_connection());
                 Err(e)
             }
             r => r
@@ -77,7 +235,85 @@ impl Read for Response {
     }
 }
 
-impl Drop for Response {
+/// Read what is left of a message body once its decoder has finished.
+fn drain(message: &mut Box<HttpMessage>) -> io::Result<usize> {
+    io::copy(message, &mut io::sink()).map(|_| 0)
+}
+
+/// The body of a `Response`, possibly being decoded.
+enum Body {
+    Plain(MessageReader),
+    GzipHeader(MessageReader),
+    Gzip(GzDecoder<MessageReader>),
+    Deflate(ZlibDecoder<MessageReader>),
+    /// A body that wasn't read from a message.
+    Bytes(Cursor<Vec<u8>>),
+    /// The gzip header could not be read, and the connection has been closed.
+    Invalid,
+}
+
+impl Body {
+    fn message(&mut self) -> Option<&mut Box<HttpMessage>> {
+        match *self {
+            Body::Plain(ref mut rdr) | Body::GzipHeader(ref mut rdr) => Some(&mut rdr.message),
+            Body::Gzip(ref mut decoder) => Some(&mut decoder.get_mut().message),
+            Body::Deflate(ref mut decoder) => Some(&mut decoder.get_mut().message),
+            Body::Bytes(_) | Body::Invalid => None
+        }
+    }
+}
+
+impl fmt::Debug for Body {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Body::Plain(ref rdr) => write!(f, "Plain({:?})", rdr.message),
+            Body::GzipHeader(ref rdr) => write!(f, "Gzip({:?})", rdr.message),
+            Body::Gzip(ref decoder) => write!(f, "Gzip({:?})", decoder.get_ref().message),
+            Body::Deflate(ref decoder) => write!(f, "Deflate({:?})", decoder.get_ref().message),
+            Body::Bytes(ref bytes) => write!(f, "Bytes({} bytes)", bytes.get_ref().len()),
+            Body::Invalid => f.write_str("Invalid"),
+        }
+    }
+}
+
+/// Reads the body from the `HttpMessage`, and decides whether the connection
+/// can be reused once the body is done with.
+struct MessageReader {
+    message: Box<HttpMessage>,
+    keep_alive: bool,
+    deadline: Option<Instant>,
+    read_timeout: Option<Duration>,
+}
+
+impl Read for MessageReader {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let deadline = match self.deadline {
+            Some(deadline) => deadline,
+            None => return self.message.read(buf)
+        };
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "Response deadline passed"));
+        }
+        // a slow server can't hold a read open past the deadline
+        let remaining = deadline - now;
+        let timeout = match self.read_timeout {
+            Some(timeout) if timeout < remaining => timeout,
+            _ => remaining
+        };
+        try!(self.message.set_read_timeout(Some(timeout)));
+        match self.message.read(buf) {
+            // sockets report a read timing out as `WouldBlock` on unix
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                Err(io::Error::new(io::ErrorKind::TimedOut, "Response read timed out"))
+            },
+            r => r
+        }
+    }
+}
+
+impl Drop for MessageReader {
     fn drop(&mut self) {
         // if not drained, theres old bits in the Reader. we can't reuse this,
         // since those old bits would end up in new Responses
@@ -86,7 +322,7 @@ impl Drop for Response {
         // server has agreed to keep the connection open
         let is_drained = !self.message.has_body();
         trace!("Response.drop is_drained={}", is_drained);
-        if !(is_drained && http::should_keep_alive(self.version, &self.headers)) {
+        if !(is_drained && self.keep_alive) {
             trace!("Response.drop closing connection");
             if let Err(e) = self.message.close_connection() {
                 error!("Response.drop error closing connection: {}", e);
@@ -97,11 +333,14 @@ impl Drop for Response {
 
 #[cfg(test)]
 mod tests {
-    use std::io::{self, Read};
+    use std::collections::BTreeMap;
+    use std::io::{self, Read, Write};
 
+    use flate2::Compression;
+    use flate2::write::{GzEncoder, ZlibEncoder};
     use url::Url;
 
-    use header::TransferEncoding;
+    use header::{Charset, ContentEncoding, ContentLength, TransferEncoding};
     use header::Encoding;
     use http::HttpMessage;
     use mock::MockStream;
@@ -230,4 +469,160 @@ mod tests {
 
         assert!(Response::new(url, Box::new(stream)).is_err());
     }
+
+    fn encoded_response(encoding: &str, body: &[u8]) -> Response {
+        let mut input = format!("\
+            HTTP/1.1 200 OK\r\n\
+            Content-Encoding: {}\r\n\
+            Content-Length: {}\r\n\
+            \r\n", encoding, body.len()).into_bytes();
+        input.extend(body);
+        let url = Url::parse("http://hyper.rs").unwrap();
+        Response::new(url, Box::new(MockStream::with_input(&input))).unwrap()
+    }
+
+    #[test]
+    fn test_decode_gzip() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::Default);
+        encoder.write_all(b"hello gzip").unwrap();
+        let mut res = encoded_response("gzip", &encoder.finish().unwrap());
+
+        assert!(res.decode_content());
+        assert!(res.headers.get::<ContentEncoding>().is_none());
+        assert!(res.headers.get::<ContentLength>().is_none());
+        assert_eq!(read_to_string(res).unwrap(), "hello gzip");
+    }
+
+    #[test]
+    fn test_decode_deflate() {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::Default);
+        encoder.write_all(b"hello deflate").unwrap();
+        let mut res = encoded_response("deflate", &encoder.finish().unwrap());
+
+        assert!(res.decode_content());
+        assert_eq!(read_to_string(res).unwrap(), "hello deflate");
+    }
+
+    #[test]
+    fn test_decode_empty_gzip() {
+        let mut res = encoded_response("gzip", b"");
+        assert!(res.decode_content());
+        assert_eq!(res.read(&mut [0; 8]).unwrap(), 0);
+        assert_eq!(res.read(&mut [0; 8]).unwrap(), 0);
+        assert_eq!(read_to_string(res).unwrap(), "");
+    }
+
+    #[test]
+    fn test_empty_read_keeps_body() {
+        let mut res = encoded_response("identity", b"plain");
+        assert_eq!(res.read(&mut []).unwrap(), 0);
+        assert_eq!(read_to_string(res).unwrap(), "plain");
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::Default);
+        encoder.write_all(b"hello gzip").unwrap();
+        let mut res = encoded_response("gzip", &encoder.finish().unwrap());
+        assert!(res.decode_content());
+        assert_eq!(res.read(&mut []).unwrap(), 0);
+        assert_eq!(read_to_string(res).unwrap(), "hello gzip");
+    }
+
+    #[test]
+    fn test_decode_invalid_gzip() {
+        let mut res = encoded_response("gzip", b"not gzip at all");
+        assert!(res.decode_content());
+        assert!(read_to_string(res).is_err());
+    }
+
+    #[test]
+    fn test_decode_identity() {
+        let mut res = encoded_response("identity", b"plain");
+        assert!(!res.decode_content());
+        assert_eq!(res.headers.get(), Some(&ContentLength(5)));
+        assert_eq!(read_to_string(res).unwrap(), "plain");
+    }
+
+    fn typed_response(content_type: &str, body: &[u8]) -> Response {
+        let mut input = format!("\
+            HTTP/1.1 200 OK\r\n\
+            Content-Type: {}\r\n\
+            Content-Length: {}\r\n\
+            \r\n", content_type, body.len()).into_bytes();
+        input.extend(body);
+        let url = Url::parse("http://hyper.rs").unwrap();
+        Response::new(url, Box::new(MockStream::with_input(&input))).unwrap()
+    }
+
+    #[test]
+    fn test_json() {
+        let mut res = typed_response("application/json; charset=utf-8", br#"{"a": [1, 2]}"#);
+        let value: BTreeMap<String, Vec<u32>> = res.json().unwrap();
+        assert_eq!(value.get("a"), Some(&vec![1, 2]));
+
+        let mut res = typed_response("application/problem+json", b"\xef\xbb\xbf[true]");
+        let value: Vec<bool> = res.json().unwrap();
+        assert_eq!(value, vec![true]);
+    }
+
+    #[test]
+    fn test_json_wrong_content_type() {
+        let mut res = typed_response("text/html", b"<html></html>");
+        match res.json::<Vec<u32>>() {
+            Err(::Error::ContentType(Some(mime))) => assert_eq!(mime.to_string(), "text/html"),
+            other => panic!("expected ContentType error, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_json_invalid() {
+        let mut res = typed_response("application/json", b"[1, ");
+        match res.json::<Vec<u32>>() {
+            Err(::Error::Json(..)) => (),
+            other => panic!("expected Json error, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_text() {
+        let mut res = typed_response("text/plain", "caf\u{e9}".as_bytes());
+        assert_eq!(res.text().unwrap(), "caf\u{e9}");
+
+        let mut res = typed_response("text/plain; charset=ISO-8859-1", b"caf\xe9");
+        assert_eq!(res.text().unwrap(), "caf\u{e9}");
+
+        let mut res = typed_response("text/plain; charset=windows-1252", b"\x93hi\x94");
+        assert_eq!(res.text().unwrap(), "\u{201c}hi\u{201d}");
+
+        let mut res = typed_response("application/json; charset=iso-8859-1", b"[\"caf\xe9\"]");
+        assert_eq!(res.json::<Vec<String>>().unwrap(), vec!["caf\u{e9}".to_owned()]);
+    }
+
+    #[test]
+    fn test_text_invalid() {
+        let mut res = typed_response("text/plain; charset=us-ascii", b"caf\xe9");
+        match res.text() {
+            Err(::Error::Charset(Charset::Us_Ascii)) => (),
+            other => panic!("expected Charset error, got {:?}", other)
+        }
+
+        let mut res = typed_response("text/plain; charset=ebcdic", b"");
+        match res.text() {
+            Err(::Error::Charset(Charset::Ext(ref charset))) if charset == "EBCDIC" => (),
+            other => panic!("expected Charset error, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_with_body() {
+        use header::{ContentType, Headers};
+
+        let mut headers = Headers::new();
+        headers.set(ContentType::plaintext());
+        let url = Url::parse("http://hyper.rs").unwrap();
+        let mut res = Response::with_body(url, status::StatusCode::NotFound, headers,
+                                          b"not here".to_vec());
+        assert_eq!(res.status_raw().0, 404);
+        assert_eq!(res.status_raw().1, "Not Found");
+        assert_eq!(res.version, version::HttpVersion::Http11);
+        assert_eq!(res.text().unwrap(), "not here");
+    }
 }
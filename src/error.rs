@@ -6,6 +6,9 @@ use std::str::Utf8Error;
 use std::string::FromUtf8Error;
 
 use httparse;
+use header::Charset as HeaderCharset;
+use mime::Mime;
+use serialize::json;
 use url;
 use solicit::http::HttpError as Http2Error;
 
@@ -22,7 +25,13 @@ use self::Error::{
     Ssl,
     TooLarge,
     Http2,
-    Utf8
+    Utf8,
+    TooManyRedirects,
+    Redirect,
+    ContentType,
+    Json,
+    Charset,
+    Timeout
 };
 
 
@@ -52,6 +61,21 @@ pub enum Error {
     Http2(Http2Error),
     /// Parsing a field as string failed
     Utf8(Utf8Error),
+    /// A request followed more redirects than the `Client` allows.
+    TooManyRedirects,
+    /// A `RedirectPolicy` refused to follow a redirect.
+    Redirect(Box<StdError + Send + Sync>),
+    /// A body did not have the expected `Content-Type`, such as when
+    /// decoding a body that isn't JSON as JSON.
+    ContentType(Option<Mime>),
+    /// An error encoding or decoding JSON.
+    Json(Box<StdError + Send + Sync>),
+    /// A body could not be decoded as text in this charset, either because
+    /// the charset isn't supported or because the body isn't valid in it.
+    Charset(HeaderCharset),
+    /// A request did not complete within one of its timeouts, or before
+    /// its deadline.
+    Timeout,
 
     #[doc(hidden)]
     __Nonexhaustive(Void)
@@ -85,6 +109,12 @@ impl StdError for Error {
             Ssl(ref e) => e.description(),
             Http2(ref e) => e.description(),
             Utf8(ref e) => e.description(),
+            TooManyRedirects => "Too many redirects",
+            Redirect(ref e) => e.description(),
+            ContentType(_) => "Unexpected Content-Type",
+            Json(ref e) => e.description(),
+            Charset(_) => "Body could not be decoded in its charset",
+            Timeout => "Timed out",
             Error::__Nonexhaustive(ref void) =>  match *void {}
         }
     }
@@ -93,6 +123,8 @@ impl StdError for Error {
         match *self {
             Io(ref error) => Some(error),
             Ssl(ref error) => Some(&**error),
+            Redirect(ref error) => Some(&**error),
+            Json(ref error) => Some(&**error),
             Uri(ref error) => Some(error),
             Http2(ref error) => Some(error),
             _ => None,
@@ -134,6 +166,18 @@ impl From<FromUtf8Error> for Error {
     }
 }
 
+impl From<json::DecoderError> for Error {
+    fn from(err: json::DecoderError) -> Error {
+        Json(Box::new(err))
+    }
+}
+
+impl From<json::EncoderError> for Error {
+    fn from(err: json::EncoderError) -> Error {
+        Json(Box::new(err))
+    }
+}
+
 impl From<httparse::Error> for Error {
     fn from(err: httparse::Error) -> Error {
         match err {
@@ -159,6 +203,7 @@ mod tests {
     use std::error::Error as StdError;
     use std::io;
     use httparse;
+    use serialize::json;
     use solicit::http::HttpError as Http2Error;
     use url;
     use super::Error;
@@ -202,6 +247,7 @@ mod tests {
         from_and_cause!(io::Error::new(io::ErrorKind::Other, "other") => Io(..));
         from_and_cause!(url::ParseError::EmptyHost => Uri(..));
         from_and_cause!(Http2Error::UnknownStreamId => Http2(..));
+        from_and_cause!(json::DecoderError::MissingFieldError("a".to_owned()) => Json(..));
 
         from!(httparse::Error::HeaderName => Header);
         from!(httparse::Error::HeaderName => Header);
@@ -41,6 +41,7 @@ pub use self::if_range::IfRange;
 pub use self::last_modified::LastModified;
 pub use self::location::Location;
 pub use self::pragma::Pragma;
+pub use self::proxy_authorization::ProxyAuthorization;
 pub use self::range::{Range, ByteRangeSpec};
 pub use self::referer::Referer;
 pub use self::server::Server;
@@ -386,6 +387,7 @@ mod if_unmodified_since;
 mod last_modified;
 mod location;
 mod pragma;
+mod proxy_authorization;
 mod range;
 mod referer;
 mod server;
@@ -96,6 +96,50 @@ impl Charset {
     }
 }
 
+/// The characters of bytes 0x80 to 0x9F in Windows-1252, where it differs
+/// from ISO-8859-1. Bytes without a character are `None`.
+const WINDOWS_1252: [Option<char>; 32] = [
+    Some('\u{20AC}'), None, Some('\u{201A}'), Some('\u{0192}'),
+    Some('\u{201E}'), Some('\u{2026}'), Some('\u{2020}'), Some('\u{2021}'),
+    Some('\u{02C6}'), Some('\u{2030}'), Some('\u{0160}'), Some('\u{2039}'),
+    Some('\u{0152}'), None, Some('\u{017D}'), None,
+    None, Some('\u{2018}'), Some('\u{2019}'), Some('\u{201C}'),
+    Some('\u{201D}'), Some('\u{2022}'), Some('\u{2013}'), Some('\u{2014}'),
+    Some('\u{02DC}'), Some('\u{2122}'), Some('\u{0161}'), Some('\u{203A}'),
+    Some('\u{0153}'), None, Some('\u{017E}'), Some('\u{0178}'),
+];
+
+impl Charset {
+    /// Decode text encoded in this charset.
+    ///
+    /// UTF-8, US-ASCII, ISO-8859-1 and Windows-1252 are supported. Returns
+    /// `Error::Charset` if the charset isn't supported, or the bytes are not
+    /// valid in it.
+    pub fn decode(&self, bytes: &[u8]) -> ::Result<String> {
+        let invalid = || ::Error::Charset(self.clone());
+        match *self {
+            Us_Ascii => {
+                if bytes.iter().any(|&b| b >= 0x80) {
+                    return Err(invalid());
+                }
+                Ok(bytes.iter().map(|&b| b as char).collect())
+            },
+            Iso_8859_1 => Ok(bytes.iter().map(|&b| b as char).collect()),
+            Ext(ref s) => match &s.to_ascii_uppercase()[..] {
+                "UTF-8" | "UTF8" => String::from_utf8(bytes.to_vec()).map_err(|_| invalid()),
+                "WINDOWS-1252" | "CP1252" => bytes.iter().map(|&b| match b {
+                    0x80...0x9F => WINDOWS_1252[(b - 0x80) as usize].ok_or_else(&invalid),
+                    b => Ok(b as char)
+                }).collect(),
+                "LATIN1" | "ISO8859-1" | "ISO_8859-1" => Iso_8859_1.decode(bytes),
+                "ASCII" => Us_Ascii.decode(bytes).map_err(|_| invalid()),
+                _ => Err(invalid())
+            },
+            _ => Err(invalid())
+        }
+    }
+}
+
 impl Display for Charset {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.write_str(self.name())
@@ -149,3 +193,29 @@ fn test_display() {
     assert_eq!("US-ASCII", format!("{}", Us_Ascii));
     assert_eq!("ABCD", format!("{}", Ext("ABCD".to_owned())));
 }
+
+#[test]
+fn test_decode() {
+    let utf8 = Ext("utf-8".to_owned());
+    assert_eq!(utf8.decode("caf\u{e9}".as_bytes()).unwrap(), "caf\u{e9}");
+    assert_eq!(Us_Ascii.decode(b"plain").unwrap(), "plain");
+    assert_eq!(Iso_8859_1.decode(b"caf\xe9").unwrap(), "caf\u{e9}");
+    let cp1252 = Ext("WINDOWS-1252".to_owned());
+    assert_eq!(cp1252.decode(b"\x93caf\xe9\x94 \x80").unwrap(), "\u{201c}caf\u{e9}\u{201d} \u{20ac}");
+}
+
+#[test]
+fn test_decode_invalid() {
+    fn charset_of(res: ::Result<String>) -> Charset {
+        match res {
+            Err(::Error::Charset(charset)) => charset,
+            other => panic!("expected Charset error, got {:?}", other)
+        }
+    }
+
+    assert_eq!(charset_of(Us_Ascii.decode(b"caf\xe9")), Us_Ascii);
+    assert_eq!(charset_of(Ext("UTF-8".to_owned()).decode(b"caf\xe9")), Ext("UTF-8".to_owned()));
+    assert_eq!(charset_of(Ext("WINDOWS-1252".to_owned()).decode(b"\x81")),
+               Ext("WINDOWS-1252".to_owned()));
+    assert_eq!(charset_of(Shift_Jis.decode(b"")), Shift_Jis);
+}
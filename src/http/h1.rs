@@ -4,7 +4,6 @@ use std::cmp::min;
 use std::fmt;
 use std::io::{self, Write, BufWriter, BufRead, Read};
 use std::net::Shutdown;
-#[cfg(feature = "timeouts")]
 use std::time::Duration;
 
 use httparse;
@@ -91,6 +90,7 @@ impl Stream {
 /// An implementation of the `HttpMessage` trait for HTTP/1.1.
 #[derive(Debug)]
 pub struct Http11Message {
+    is_proxied: bool,
     method: Option<Method>,
     stream: Wrapper<Stream>,
 }
@@ -131,6 +131,7 @@ impl HttpMessage for Http11Message {
                             io::ErrorKind::Other,
                             "")));
         let mut method = None;
+        let is_proxied = self.is_proxied;
         self.stream.map_in_place(|stream: Stream| -> Stream {
             let stream = match stream {
                 Stream::Idle(stream) => stream,
@@ -143,11 +144,16 @@ impl HttpMessage for Http11Message {
             };
             let mut stream = BufWriter::new(stream);
 
-            let mut uri = head.url.serialize_path().unwrap();
-            if let Some(ref q) = head.url.query {
-                uri.push('?');
-                uri.push_str(&q[..]);
-            }
+            let uri = if is_proxied {
+                head.url.serialize_no_fragment()
+            } else {
+                let mut uri = head.url.serialize_path().unwrap();
+                if let Some(ref q) = head.url.query {
+                    uri.push('?');
+                    uri.push_str(&q[..]);
+                }
+                uri
+            };
 
             let version = version::HttpVersion::Http11;
             debug!("request line: {:?} {:?} {:?}", head.method, uri, version);
@@ -341,13 +347,11 @@ impl HttpMessage for Http11Message {
         }
     }
 
-    #[cfg(feature = "timeouts")]
     #[inline]
     fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
         self.get_ref().set_read_timeout(dur)
     }
 
-    #[cfg(feature = "timeouts")]
     #[inline]
     fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
         self.get_ref().set_write_timeout(dur)
@@ -358,6 +362,11 @@ impl HttpMessage for Http11Message {
         try!(self.get_mut().close(Shutdown::Both));
         Ok(())
     }
+
+    #[inline]
+    fn set_proxied(&mut self, val: bool) {
+        self.is_proxied = val;
+    }
 }
 
 impl Http11Message {
@@ -394,6 +403,7 @@ impl Http11Message {
     /// the peer.
     pub fn with_stream(stream: Box<NetworkStream + Send>) -> Http11Message {
         Http11Message {
+            is_proxied: false,
             method: None,
             stream: Wrapper::new(Stream::new(stream)),
         }
@@ -433,7 +443,12 @@ pub struct Http11Protocol {
 
 impl Protocol for Http11Protocol {
     fn new_message(&self, host: &str, port: u16, scheme: &str) -> ::Result<Box<HttpMessage>> {
-        let stream = try!(self.connector.connect(host, port, scheme)).into();
+        self.new_message_timeout(host, port, scheme, None)
+    }
+
+    fn new_message_timeout(&self, host: &str, port: u16, scheme: &str,
+                           timeout: Option<Duration>) -> ::Result<Box<HttpMessage>> {
+        let stream = try!(self.connector.connect_timeout(host, port, scheme, timeout)).into();
 
         Ok(Box::new(Http11Message::with_stream(stream)))
     }
@@ -461,6 +476,11 @@ impl<C: NetworkConnector<Stream=S> + Send + Sync, S: NetworkStream + Send>
         -> ::Result<Box<NetworkStream + Send>> {
         Ok(try!(self.0.connect(host, port, scheme)).into())
     }
+    #[inline]
+    fn connect_timeout(&self, host: &str, port: u16, scheme: &str, timeout: Option<Duration>)
+        -> ::Result<Box<NetworkStream + Send>> {
+        Ok(try!(self.0.connect_timeout(host, port, scheme, timeout)).into())
+    }
 }
 
 struct Connector(Box<NetworkConnector<Stream=Box<NetworkStream + Send>> + Send + Sync>);
@@ -472,6 +492,11 @@ impl NetworkConnector for Connector {
         -> ::Result<Box<NetworkStream + Send>> {
         Ok(try!(self.0.connect(host, port, scheme)).into())
     }
+    #[inline]
+    fn connect_timeout(&self, host: &str, port: u16, scheme: &str, timeout: Option<Duration>)
+        -> ::Result<Box<NetworkStream + Send>> {
+        Ok(try!(self.0.connect_timeout(host, port, scheme, timeout)).into())
+    }
 }
 
 
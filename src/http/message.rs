@@ -6,9 +6,7 @@ use std::fmt::Debug;
 use std::io::{Read, Write};
 use std::mem;
 
-#[cfg(feature = "timeouts")]
 use std::io;
-#[cfg(feature = "timeouts")]
 use std::time::Duration;
 
 use typeable::Typeable;
@@ -26,6 +24,15 @@ use traitobject;
 pub trait Protocol {
     /// Creates a fresh `HttpMessage` bound to the given host, based on the given protocol scheme.
     fn new_message(&self, host: &str, port: u16, scheme: &str) -> ::Result<Box<HttpMessage>>;
+
+    /// Creates a fresh `HttpMessage`, giving up on connecting after `timeout` has passed.
+    ///
+    /// The default implementation ignores the timeout, and calls `new_message`.
+    fn new_message_timeout(&self, host: &str, port: u16, scheme: &str,
+                           timeout: Option<Duration>) -> ::Result<Box<HttpMessage>> {
+        let _ = timeout;
+        self.new_message(host, port, scheme)
+    }
 }
 
 /// Describes a request.
@@ -65,15 +72,30 @@ pub trait HttpMessage: Write + Read + Send + Any + Typeable + Debug {
     /// the response body.
     fn get_incoming(&mut self) -> ::Result<ResponseHead>;
     /// Set the read timeout duration for this message.
-    #[cfg(feature = "timeouts")]
-    fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()>;
+    ///
+    /// The default implementation ignores the timeout.
+    fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        let _ = dur;
+        Ok(())
+    }
     /// Set the write timeout duration for this message.
-    #[cfg(feature = "timeouts")]
-    fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()>;
+    ///
+    /// The default implementation ignores the timeout.
+    fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        let _ = dur;
+        Ok(())
+    }
     /// Closes the underlying HTTP connection.
     fn close_connection(&mut self) -> ::Result<()>;
     /// Returns whether the incoming message has a body.
     fn has_body(&self) -> bool;
+    /// Called when the Client sends this message through a proxy.
+    ///
+    /// A proxied HTTP/1.1 message needs to use the absolute-form of the
+    /// request target. The default implementation ignores this.
+    fn set_proxied(&mut self, val: bool) {
+        trace!("default set_proxied({:?})", val);
+    }
 }
 
 impl HttpMessage {
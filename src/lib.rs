@@ -136,6 +136,7 @@ extern crate openssl;
 #[cfg(feature = "serde-serialization")]
 extern crate serde;
 extern crate cookie;
+extern crate flate2;
 extern crate unicase;
 extern crate httparse;
 extern crate num_cpus;
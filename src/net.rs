@@ -1,15 +1,20 @@
 //! A collection of traits abstracting over Listeners and Streams.
 use std::any::{Any, TypeId};
+use std::ascii::AsciiExt;
+use std::collections::HashMap;
+use std::error::Error as StdError;
 use std::fmt;
 use std::io::{self, ErrorKind, Read, Write};
-use std::net::{SocketAddr, ToSocketAddrs, TcpStream, TcpListener, Shutdown};
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs, TcpStream, TcpListener, Shutdown};
 use std::mem;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
 #[cfg(feature = "openssl")]
 pub use self::openssl::Openssl;
-
-#[cfg(feature = "timeouts")]
-use std::time::Duration;
+#[cfg(unix)]
+pub use self::unix::{UnixConnector, UnixListener, UnixStream};
 
 use typeable::Typeable;
 use traitobject;
@@ -53,12 +58,20 @@ pub trait NetworkStream: Read + Write + Any + Send + Typeable {
     fn peer_addr(&mut self) -> io::Result<SocketAddr>;
 
     /// Set the maximum time to wait for a read to complete.
-    #[cfg(feature = "timeouts")]
-    fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()>;
+    ///
+    /// The default implementation ignores the timeout.
+    fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        let _ = dur;
+        Ok(())
+    }
 
     /// Set the maximum time to wait for a write to complete.
-    #[cfg(feature = "timeouts")]
-    fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()>;
+    ///
+    /// The default implementation ignores the timeout.
+    fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        let _ = dur;
+        Ok(())
+    }
 
     /// This will be called when Stream should no longer be kept alive.
     #[inline]
@@ -66,6 +79,16 @@ pub trait NetworkStream: Read + Write + Any + Send + Typeable {
         Ok(())
     }
 
+    /// Check whether an idle stream can no longer be used.
+    ///
+    /// A stream is stale if the peer has closed it, or if it has unexpected
+    /// data waiting to be read. This must not block. The default assumes the
+    /// stream is still usable.
+    #[inline]
+    fn is_stale(&mut self) -> bool {
+        false
+    }
+
     // Unsure about name and implementation...
 
     #[doc(hidden)]
@@ -84,6 +107,15 @@ pub trait NetworkConnector {
 
     /// Connect to a remote address.
     fn connect(&self, host: &str, port: u16, scheme: &str) -> ::Result<Self::Stream>;
+
+    /// Connect to a remote address, giving up after `timeout` has passed.
+    ///
+    /// The default implementation ignores the timeout, and calls `connect`.
+    fn connect_timeout(&self, host: &str, port: u16, scheme: &str,
+                       timeout: Option<Duration>) -> ::Result<Self::Stream> {
+        let _ = timeout;
+        self.connect(host, port, scheme)
+    }
 }
 
 impl<T: NetworkStream + Send> From<T> for Box<NetworkStream + Send> {
@@ -341,13 +373,11 @@ impl NetworkStream for HttpStream {
             self.0.peer_addr()
     }
 
-    #[cfg(feature = "timeouts")]
     #[inline]
     fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
         self.0.set_read_timeout(dur)
     }
 
-    #[cfg(feature = "timeouts")]
     #[inline]
     fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
         self.0.set_write_timeout(dur)
@@ -362,27 +392,379 @@ impl NetworkStream for HttpStream {
             err => err
         }
     }
+
+    fn is_stale(&mut self) -> bool {
+        if self.0.set_nonblocking(true).is_err() {
+            return true;
+        }
+        // an idle connection has nothing to read, so EOF, data or an
+        // error all mean it can't be reused
+        let stale = match self.0.peek(&mut [0]) {
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => false,
+            _ => true
+        };
+        self.0.set_nonblocking(false).is_err() || stale
+    }
 }
 
 /// A connector that will produce HttpStreams.
-#[derive(Debug, Clone, Default)]
-pub struct HttpConnector;
+///
+/// Every address the host resolves to is tried, racing IPv6 and IPv4 as
+/// described in [RFC 8305](https://tools.ietf.org/html/rfc8305): addresses
+/// alternate between the two families, and when an attempt hasn't connected
+/// within the Happy Eyeballs delay, the next one is started alongside it.
+/// The first connection to be established is used.
+///
+/// Host names are resolved by a `Resolve`, which is the system resolver
+/// unless another is given with `HttpConnector::with_resolver`.
+#[derive(Debug, Clone)]
+pub struct HttpConnector<R = SystemResolver> {
+    resolver: R,
+    happy_eyeballs_delay: Duration,
+}
+
+impl HttpConnector {
+    /// Create a connector with the default Happy Eyeballs delay of 250ms.
+    pub fn new() -> HttpConnector {
+        HttpConnector::with_resolver(SystemResolver)
+    }
+}
+
+impl<R: Resolve> HttpConnector<R> {
+    /// Create a connector that resolves host names with the given resolver.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hyper::Client;
+    /// use hyper::net::{HttpConnector, StaticResolver};
+    ///
+    /// let mut resolver = StaticResolver::new();
+    /// resolver.add("api.example.domain", "127.0.0.1".parse().unwrap());
+    /// let client = Client::with_connector(HttpConnector::with_resolver(resolver));
+    /// ```
+    pub fn with_resolver(resolver: R) -> HttpConnector<R> {
+        HttpConnector {
+            resolver: resolver,
+            happy_eyeballs_delay: Duration::from_millis(250),
+        }
+    }
+
+    /// The resolver of this connector.
+    pub fn resolver(&self) -> &R {
+        &self.resolver
+    }
+
+    /// Set how long to wait for a connection attempt before starting an
+    /// attempt to the next address.
+    pub fn set_happy_eyeballs_delay(&mut self, delay: Duration) {
+        self.happy_eyeballs_delay = delay;
+    }
+}
+
+impl Default for HttpConnector {
+    fn default() -> HttpConnector {
+        HttpConnector::new()
+    }
+}
 
-impl NetworkConnector for HttpConnector {
+impl<R: Resolve> NetworkConnector for HttpConnector<R> {
     type Stream = HttpStream;
 
     fn connect(&self, host: &str, port: u16, scheme: &str) -> ::Result<HttpStream> {
-        let addr = &(host, port);
-        Ok(try!(match scheme {
-            "http" => {
-                debug!("http scheme");
-                Ok(HttpStream(try!(TcpStream::connect(addr))))
+        self.connect_timeout(host, port, scheme, None)
+    }
+
+    /// Connect to a remote address, giving up on every address after
+    /// `timeout` has passed.
+    ///
+    /// Fails with an `Error::Io` holding a `ConnectError`, with the failure
+    /// of each address. Its kind is `TimedOut` when every attempt timed
+    /// out, which a `Client` reports as `Error::Timeout`.
+    fn connect_timeout(&self, host: &str, port: u16, scheme: &str,
+                       timeout: Option<Duration>) -> ::Result<HttpStream> {
+        if scheme != "http" {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "Invalid scheme for Http").into());
+        }
+        debug!("http scheme");
+        let addrs = try!(self.resolver.resolve(host, port));
+        if addrs.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound,
+                                      "No address found for host").into());
+        }
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        match happy_eyeballs(interleave(addrs), self.happy_eyeballs_delay, deadline) {
+            Ok(stream) => Ok(HttpStream(stream)),
+            Err(e) => {
+                let kind = if e.errors.iter().all(|&(_, ref e)| e.kind() == ErrorKind::TimedOut) {
+                    ErrorKind::TimedOut
+                } else {
+                    e.errors[0].1.kind()
+                };
+                Err(io::Error::new(kind, e).into())
+            }
+        }
+    }
+}
+
+/// Resolves host names to the addresses a connector connects to.
+///
+/// It is implemented for closures, as well as the resolvers below.
+pub trait Resolve {
+    /// Resolve `host` to the addresses of its `port`.
+    fn resolve(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>>;
+}
+
+impl<F> Resolve for F where F: Fn(&str, u16) -> io::Result<Vec<SocketAddr>> {
+    fn resolve(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        (*self)(host, port)
+    }
+}
+
+/// Resolves host names with the system resolver, through `ToSocketAddrs`.
+#[derive(Debug, Clone, Default)]
+pub struct SystemResolver;
+
+impl Resolve for SystemResolver {
+    fn resolve(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        // IPv6 hosts of URLs are bracketed
+        let host = host.trim_matches(|c| c == '[' || c == ']');
+        Ok(try!((host, port).to_socket_addrs()).collect())
+    }
+}
+
+/// Resolves the host names it has been given addresses for to those
+/// addresses, and others with another resolver, like curl's `--resolve`.
+///
+/// Host names are matched ignoring case.
+#[derive(Debug, Clone)]
+pub struct StaticResolver<R = SystemResolver> {
+    hosts: HashMap<String, Vec<IpAddr>>,
+    fallback: R,
+}
+
+impl StaticResolver {
+    /// Create a resolver that resolves other host names with the system
+    /// resolver.
+    pub fn new() -> StaticResolver {
+        StaticResolver::with_fallback(SystemResolver)
+    }
+}
+
+impl<R: Resolve> StaticResolver<R> {
+    /// Create a resolver that resolves other host names with `fallback`.
+    pub fn with_fallback(fallback: R) -> StaticResolver<R> {
+        StaticResolver {
+            hosts: HashMap::new(),
+            fallback: fallback,
+        }
+    }
+
+    /// Add an address for `host`.
+    pub fn add<H: Into<String>>(&mut self, host: H, ip: IpAddr) {
+        let mut host = host.into();
+        host.make_ascii_lowercase();
+        self.hosts.entry(host).or_insert_with(Vec::new).push(ip);
+    }
+}
+
+impl<R: Resolve> Resolve for StaticResolver<R> {
+    fn resolve(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        match self.hosts.get(&host.to_ascii_lowercase()) {
+            Some(ips) => {
+                trace!("static addresses for {:?}: {:?}", host, ips);
+                Ok(ips.iter().map(|&ip| SocketAddr::new(ip, port)).collect())
             },
-            _ => {
-                Err(io::Error::new(io::ErrorKind::InvalidInput,
-                                "Invalid scheme for Http"))
+            None => self.fallback.resolve(host, port)
+        }
+    }
+}
+
+/// Keeps the addresses another resolver resolves host names to for a
+/// time to live, and resolves them again once it has passed.
+///
+/// Failures to resolve a host name are not kept. Clones of a
+/// `CachingResolver` share the addresses it keeps.
+#[derive(Debug, Clone)]
+pub struct CachingResolver<R = SystemResolver> {
+    resolver: R,
+    ttl: Duration,
+    cache: Arc<Mutex<HashMap<String, (Instant, Vec<SocketAddr>)>>>,
+}
+
+impl CachingResolver {
+    /// Create a resolver that keeps what the system resolver resolves for
+    /// `ttl`.
+    pub fn new(ttl: Duration) -> CachingResolver {
+        CachingResolver::with_resolver(SystemResolver, ttl)
+    }
+}
+
+impl<R: Resolve> CachingResolver<R> {
+    /// Create a resolver that keeps what `resolver` resolves for `ttl`.
+    pub fn with_resolver(resolver: R, ttl: Duration) -> CachingResolver<R> {
+        CachingResolver {
+            resolver: resolver,
+            ttl: ttl,
+            cache: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Forget every address that has been kept.
+    pub fn clear(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+}
+
+impl<R: Resolve> Resolve for CachingResolver<R> {
+    fn resolve(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        let key = host.to_ascii_lowercase();
+        if let Some(&(expires, ref addrs)) = self.cache.lock().unwrap().get(&key) {
+            if Instant::now() < expires {
+                trace!("cached addresses for {:?}: {:?}", host, addrs);
+                return Ok(addrs.iter().map(|addr| SocketAddr::new(addr.ip(), port)).collect());
+            }
+        }
+        // resolved without the lock, so that other hosts aren't held up
+        let addrs = try!(self.resolver.resolve(host, port));
+        let now = Instant::now();
+        let mut cache = self.cache.lock().unwrap();
+        // hosts that aren't resolved again would otherwise be kept forever
+        cache.retain(|_, &mut (expires, _)| now < expires);
+        cache.insert(key, (now + self.ttl, addrs.clone()));
+        Ok(addrs)
+    }
+}
+
+/// The failures of each address a connector tried to connect to.
+///
+/// `HttpConnector` returns this inside of an `io::Error`, whose kind is that
+/// of the failure of the first address tried.
+#[derive(Debug)]
+pub struct ConnectError {
+    errors: Vec<(SocketAddr, io::Error)>,
+}
+
+impl ConnectError {
+    /// Each address that was tried, and why connecting to it failed, in
+    /// the order the attempts were started.
+    pub fn errors(&self) -> &[(SocketAddr, io::Error)] {
+        &self.errors
+    }
+}
+
+impl fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(f.write_str("Could not connect to any address"));
+        for (i, &(ref addr, ref e)) in self.errors.iter().enumerate() {
+            try!(write!(f, "{} {}: {}", if i == 0 { ":" } else { ";" }, addr, e));
+        }
+        Ok(())
+    }
+}
+
+impl StdError for ConnectError {
+    fn description(&self) -> &str {
+        "Could not connect to any address"
+    }
+}
+
+/// Order addresses so that they alternate between address families,
+/// starting with the family of the first address, as in RFC 8305,
+/// section 4.
+fn interleave(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let first_is_v6 = addrs[0].is_ipv6();
+    let (mut first, mut second): (Vec<_>, Vec<_>) = addrs.into_iter()
+        .partition(|addr| addr.is_ipv6() == first_is_v6);
+    let mut addrs = Vec::with_capacity(first.len() + second.len());
+    first.reverse();
+    second.reverse();
+    loop {
+        match (first.pop(), second.pop()) {
+            (None, None) => return addrs,
+            (a, b) => {
+                addrs.extend(a);
+                addrs.extend(b);
+            }
+        }
+    }
+}
+
+/// Connect to `addr`, giving up once `deadline` has passed.
+fn connect_before(addr: SocketAddr, deadline: Option<Instant>) -> io::Result<TcpStream> {
+    match deadline {
+        Some(deadline) => {
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(io::Error::new(ErrorKind::TimedOut, "connection timed out"));
+            }
+            TcpStream::connect_timeout(&addr, deadline - now)
+        },
+        None => TcpStream::connect(&addr)
+    }
+}
+
+/// Connect to the first of `addrs` that accepts, starting an attempt to the
+/// next address whenever one fails, or `delay` passes without any attempt
+/// connecting.
+fn happy_eyeballs(addrs: Vec<SocketAddr>, delay: Duration, deadline: Option<Instant>)
+        -> Result<TcpStream, ConnectError> {
+    if addrs.len() == 1 {
+        // nothing to race
+        let addr = addrs[0];
+        debug!("connecting to {}", addr);
+        return connect_before(addr, deadline)
+            .map_err(|e| ConnectError { errors: vec![(addr, e)] });
+    }
+
+    let (tx, rx) = mpsc::channel();
+    // kept in the order the attempts were started, whichever fails first
+    let mut errors = vec![];
+    let mut addrs = addrs.into_iter().peekable();
+    let mut attempts = 0;
+    loop {
+        if let Some(addr) = addrs.next() {
+            debug!("connecting to {}", addr);
+            let i = errors.len();
+            errors.push(None);
+            let tx = tx.clone();
+            // attempts that lose the race finish on their own, and their
+            // streams are dropped
+            thread::spawn(move || {
+                let _ = tx.send((i, addr, connect_before(addr, deadline)));
+            });
+            attempts += 1;
+        } else if attempts == 0 {
+            let errors = errors.into_iter()
+                .map(|e| e.expect("every attempt has failed"))
+                .collect();
+            return Err(ConnectError { errors: errors });
+        }
+
+        loop {
+            let res = if addrs.peek().is_some() {
+                match rx.recv_timeout(delay) {
+                    Ok(res) => res,
+                    // time to start the next attempt
+                    Err(_) => break
+                }
+            } else {
+                rx.recv().expect("connector holds a sender")
+            };
+            match res {
+                (_, addr, Ok(stream)) => {
+                    debug!("connected to {}", addr);
+                    return Ok(stream);
+                },
+                (i, addr, Err(e)) => {
+                    debug!("connect to {} failed: {:?}", addr, e);
+                    errors[i] = Some((addr, e));
+                    attempts -= 1;
+                    break;
+                }
             }
-        }))
+        }
     }
 }
 
@@ -415,6 +797,209 @@ impl<F> NetworkConnector for F where F: Fn(&str, u16, &str) -> io::Result<TcpStr
     }
 }
 
+/// A connector that reaches hosts through a SOCKS5 proxy.
+///
+/// The SOCKS5 server is connected to with the inner connector, after which
+/// the handshake described in [RFC1928](https://tools.ietf.org/html/rfc1928)
+/// asks it to connect to the target host. The resulting stream then
+/// talks to the target directly, so the connector can be used with a `Pool`,
+/// or inside of an `HttpsConnector`.
+///
+/// By default, hostnames are sent to the SOCKS5 server to be resolved there.
+///
+/// # Example
+///
+/// ```no_run
+/// use hyper::Client;
+/// use hyper::net::Socks5Connector;
+///
+/// let mut socks = Socks5Connector::new("localhost", 1080);
+/// socks.set_credentials("user", "pass");
+/// let client = Client::with_connector(socks);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Socks5Connector<C = HttpConnector> {
+    connector: C,
+    host: String,
+    port: u16,
+    credentials: Option<(String, String)>,
+    remote_dns: bool,
+}
+
+impl Socks5Connector<HttpConnector> {
+    /// Create a connector that uses the SOCKS5 server at `host:port`.
+    pub fn new<H: Into<String>>(host: H, port: u16) -> Socks5Connector<HttpConnector> {
+        Socks5Connector::with_connector(HttpConnector::new(), host, port)
+    }
+}
+
+impl<C: NetworkConnector> Socks5Connector<C> where C::Stream: NetworkStream {
+    /// Create a connector that reaches the SOCKS5 server at `host:port` using
+    /// the provided connector.
+    pub fn with_connector<H: Into<String>>(connector: C, host: H, port: u16) -> Socks5Connector<C> {
+        Socks5Connector {
+            connector: connector,
+            host: host.into(),
+            port: port,
+            credentials: None,
+            remote_dns: true,
+        }
+    }
+
+    /// Authenticate with the SOCKS5 server using a username and password,
+    /// as described in [RFC1929](https://tools.ietf.org/html/rfc1929).
+    pub fn set_credentials<U, P>(&mut self, username: U, password: P)
+    where U: Into<String>, P: Into<String> {
+        self.credentials = Some((username.into(), password.into()));
+    }
+
+    /// Set whether hostnames are resolved by the SOCKS5 server, or locally
+    /// before connecting.
+    pub fn set_remote_dns(&mut self, remote: bool) {
+        self.remote_dns = remote;
+    }
+}
+
+impl<C: NetworkConnector> NetworkConnector for Socks5Connector<C> where C::Stream: NetworkStream {
+    type Stream = C::Stream;
+
+    fn connect(&self, host: &str, port: u16, scheme: &str) -> ::Result<C::Stream> {
+        self.connect_timeout(host, port, scheme, None)
+    }
+
+    /// The timeout applies to connecting to the SOCKS5 server.
+    fn connect_timeout(&self, host: &str, port: u16, scheme: &str,
+                       timeout: Option<Duration>) -> ::Result<C::Stream> {
+        if scheme != "http" {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "Invalid scheme for Socks5").into());
+        }
+        let target = if self.remote_dns {
+            match host.trim_matches(|c| c == '[' || c == ']').parse() {
+                Ok(ip) => Socks5Addr::Ip(ip),
+                Err(_) => Socks5Addr::Domain(host)
+            }
+        } else {
+            match try!((host, port).to_socket_addrs()).next() {
+                Some(addr) => Socks5Addr::Ip(addr.ip()),
+                None => return Err(io::Error::new(io::ErrorKind::NotFound,
+                                                  "No address found for host").into())
+            }
+        };
+        debug!("socks5 {}:{} to {:?}:{}", self.host, self.port, target, port);
+        let mut stream = try!(self.connector.connect_timeout(&self.host, self.port, "http",
+                                                             timeout));
+        let credentials = self.credentials.as_ref().map(|&(ref u, ref p)| (&u[..], &p[..]));
+        try!(socks5_handshake(&mut stream, target, port, credentials));
+        Ok(stream)
+    }
+}
+
+#[derive(Debug)]
+enum Socks5Addr<'a> {
+    Ip(IpAddr),
+    Domain(&'a str),
+}
+
+fn socks5_handshake<S: Read + Write>(stream: &mut S, target: Socks5Addr, port: u16,
+                                     credentials: Option<(&str, &str)>) -> ::Result<()> {
+    fn socks_error(msg: &'static str) -> ::Error {
+        io::Error::new(io::ErrorKind::InvalidData, msg).into()
+    }
+
+    // greeting, offering either no authentication or username/password
+    if credentials.is_some() {
+        try!(stream.write_all(&[5, 2, 0, 2]));
+    } else {
+        try!(stream.write_all(&[5, 1, 0]));
+    }
+    let mut reply = [0u8; 2];
+    try!(stream.read_exact(&mut reply));
+    if reply[0] != 5 {
+        return Err(socks_error("Invalid SOCKS version"));
+    }
+    match (reply[1], credentials) {
+        (0, _) => (),
+        (2, Some((username, password))) => {
+            if username.len() > 255 || password.len() > 255 {
+                return Err(socks_error("SOCKS credentials too long"));
+            }
+            let mut auth = vec![1, username.len() as u8];
+            auth.extend(username.as_bytes());
+            auth.push(password.len() as u8);
+            auth.extend(password.as_bytes());
+            try!(stream.write_all(&auth));
+            try!(stream.read_exact(&mut reply));
+            // the reply carries the version of the subnegotiation, not 5
+            if reply[0] != 1 {
+                return Err(socks_error("Invalid SOCKS authentication reply"));
+            }
+            if reply[1] != 0 {
+                return Err(io::Error::new(io::ErrorKind::PermissionDenied,
+                                          "SOCKS authentication failed").into());
+            }
+        },
+        _ => return Err(socks_error("No acceptable SOCKS authentication method"))
+    }
+
+    let mut request = vec![5, 1, 0];
+    match target {
+        Socks5Addr::Ip(IpAddr::V4(ip)) => {
+            request.push(1);
+            request.extend(&ip.octets());
+        },
+        Socks5Addr::Ip(IpAddr::V6(ip)) => {
+            request.push(4);
+            request.extend(&ip.octets());
+        },
+        Socks5Addr::Domain(domain) => {
+            if domain.len() > 255 {
+                return Err(socks_error("SOCKS domain name too long"));
+            }
+            request.push(3);
+            request.push(domain.len() as u8);
+            request.extend(domain.as_bytes());
+        }
+    }
+    request.push((port >> 8) as u8);
+    request.push(port as u8);
+    try!(stream.write_all(&request));
+
+    let mut head = [0u8; 4];
+    try!(stream.read_exact(&mut head));
+    if head[0] != 5 {
+        return Err(socks_error("Invalid SOCKS version"));
+    }
+    if head[1] != 0 {
+        let msg = match head[1] {
+            1 => "SOCKS server failure",
+            2 => "SOCKS connection not allowed by ruleset",
+            3 => "SOCKS network unreachable",
+            4 => "SOCKS host unreachable",
+            5 => "SOCKS connection refused",
+            6 => "SOCKS TTL expired",
+            7 => "SOCKS command not supported",
+            8 => "SOCKS address type not supported",
+            _ => "Unknown SOCKS error"
+        };
+        return Err(io::Error::new(io::ErrorKind::ConnectionRefused, msg).into());
+    }
+    // the bound address isn't needed, but must be read off the stream
+    let len = match head[3] {
+        1 => 4,
+        4 => 16,
+        3 => {
+            let mut len = [0u8; 1];
+            try!(stream.read_exact(&mut len));
+            len[0] as usize
+        },
+        _ => return Err(socks_error("Invalid SOCKS address type"))
+    };
+    let mut bound = vec![0u8; len + 2];
+    try!(stream.read_exact(&mut bound));
+    Ok(())
+}
+
 /// An abstraction to allow any SSL implementation to be used with HttpsStreams.
 pub trait Ssl {
     /// The protected stream.
@@ -471,7 +1056,6 @@ impl<S: NetworkStream> NetworkStream for HttpsStream<S> {
         }
     }
 
-    #[cfg(feature = "timeouts")]
     #[inline]
     fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
         match *self {
@@ -480,12 +1064,11 @@ impl<S: NetworkStream> NetworkStream for HttpsStream<S> {
         }
     }
 
-    #[cfg(feature = "timeouts")]
     #[inline]
     fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
         match *self {
-            HttpsStream::Http(ref inner) => inner.0.set_read_timeout(dur),
-            HttpsStream::Https(ref inner) => inner.set_read_timeout(dur)
+            HttpsStream::Http(ref inner) => inner.0.set_write_timeout(dur),
+            HttpsStream::Https(ref inner) => inner.set_write_timeout(dur)
         }
     }
 
@@ -496,6 +1079,14 @@ impl<S: NetworkStream> NetworkStream for HttpsStream<S> {
             HttpsStream::Https(ref mut s) => s.close(how)
         }
     }
+
+    #[inline]
+    fn is_stale(&mut self) -> bool {
+        match *self {
+            HttpsStream::Http(ref mut s) => s.is_stale(),
+            HttpsStream::Https(ref mut s) => s.is_stale()
+        }
+    }
 }
 
 /// A Http Listener over SSL.
@@ -539,37 +1130,79 @@ impl<S: Ssl + Clone> NetworkListener for HttpsListener<S> {
 
 /// A connector that can protect HTTP streams using SSL.
 #[derive(Debug, Default)]
-pub struct HttpsConnector<S: Ssl> {
-    ssl: S
+pub struct HttpsConnector<S: Ssl, C = HttpConnector> {
+    ssl: S,
+    connector: C,
 }
 
-impl<S: Ssl> HttpsConnector<S> {
+impl<S: Ssl> HttpsConnector<S, HttpConnector> {
     /// Create a new connector using the provided SSL implementation.
-    pub fn new(s: S) -> HttpsConnector<S> {
-        HttpsConnector { ssl: s }
+    pub fn new(s: S) -> HttpsConnector<S, HttpConnector> {
+        HttpsConnector::with_connector(s, HttpConnector::new())
     }
 }
 
-impl<S: Ssl> NetworkConnector for HttpsConnector<S> {
+impl<S: Ssl, C: NetworkConnector<Stream=HttpStream>> HttpsConnector<S, C> {
+    /// Create a new connector using the provided SSL implementation, which
+    /// opens the underlying `HttpStream`s with the provided connector.
+    pub fn with_connector(s: S, connector: C) -> HttpsConnector<S, C> {
+        HttpsConnector { ssl: s, connector: connector }
+    }
+}
+
+impl<S: Ssl, C: NetworkConnector<Stream=HttpStream>> NetworkConnector for HttpsConnector<S, C> {
     type Stream = HttpsStream<S::Stream>;
 
     fn connect(&self, host: &str, port: u16, scheme: &str) -> ::Result<Self::Stream> {
-        let addr = &(host, port);
+        self.connect_timeout(host, port, scheme, None)
+    }
+
+    fn connect_timeout(&self, host: &str, port: u16, scheme: &str,
+                       timeout: Option<Duration>) -> ::Result<Self::Stream> {
         if scheme == "https" {
             debug!("https scheme");
-            let stream = HttpStream(try!(TcpStream::connect(addr)));
+            let stream = try!(self.connector.connect_timeout(host, port, "http", timeout));
             self.ssl.wrap_client(stream, host).map(HttpsStream::Https)
         } else {
-            HttpConnector.connect(host, port, scheme).map(HttpsStream::Http)
+            self.connector.connect_timeout(host, port, scheme, timeout).map(HttpsStream::Http)
         }
     }
 }
 
 
+/// An `Ssl` implementation that refuses to protect any stream.
+///
+/// Used in place of a real SSL implementation when hyper is built without
+/// one, so that connecting to `https` URLs fails with an error.
+#[derive(Debug, Clone, Default)]
+pub struct NoSsl;
+
+impl Ssl for NoSsl {
+    type Stream = HttpStream;
+
+    fn wrap_client(&self, _stream: HttpStream, _host: &str) -> ::Result<HttpStream> {
+        Err(io::Error::new(io::ErrorKind::InvalidInput,
+                           "No SSL implementation available").into())
+    }
+
+    fn wrap_server(&self, _stream: HttpStream) -> ::Result<HttpStream> {
+        Err(io::Error::new(io::ErrorKind::InvalidInput,
+                           "No SSL implementation available").into())
+    }
+}
+
 #[cfg(not(feature = "openssl"))]
 #[doc(hidden)]
 pub type DefaultConnector = HttpConnector;
 
+#[cfg(not(feature = "openssl"))]
+#[doc(hidden)]
+pub type DefaultSsl = NoSsl;
+
+#[cfg(feature = "openssl")]
+#[doc(hidden)]
+pub type DefaultSsl = self::openssl::Openssl;
+
 #[cfg(feature = "openssl")]
 #[doc(hidden)]
 pub type DefaultConnector = HttpsConnector<self::openssl::Openssl>;
@@ -580,7 +1213,6 @@ mod openssl {
     use std::net::{SocketAddr, Shutdown};
     use std::path::Path;
     use std::sync::Arc;
-    #[cfg(feature = "timeouts")]
     use std::time::Duration;
 
     use openssl::ssl::{Ssl, SslContext, SslStream, SslMethod, SSL_VERIFY_NONE};
@@ -660,13 +1292,11 @@ mod openssl {
             self.get_mut().peer_addr()
         }
 
-        #[cfg(feature = "timeouts")]
         #[inline]
         fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
             self.get_ref().set_read_timeout(dur)
         }
 
-        #[cfg(feature = "timeouts")]
         #[inline]
         fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
             self.get_ref().set_write_timeout(dur)
@@ -675,13 +1305,242 @@ mod openssl {
         fn close(&mut self, how: Shutdown) -> io::Result<()> {
             self.get_mut().close(how)
         }
+
+        #[inline]
+        fn is_stale(&mut self) -> bool {
+            self.get_mut().is_stale()
+        }
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::ffi::OsStr;
+    use std::fmt;
+    use std::io::{self, ErrorKind, Read, Write};
+    use std::net::{SocketAddr, Shutdown};
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::net;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    use serialize::hex::{FromHex, ToHex};
+    use url::{Url, ParseError};
+
+    use net::{NetworkConnector, NetworkListener, NetworkStream};
+
+    fn no_socket_addr() -> io::Error {
+        io::Error::new(ErrorKind::AddrNotAvailable, "Unix sockets have no SocketAddr")
+    }
+
+    /// A listener on a Unix domain socket.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hyper::Server;
+    /// use hyper::net::UnixListener;
+    /// # use hyper::server::{Request, Response};
+    /// # fn hello(_: Request, res: Response) { res.send(b"Hello").unwrap(); }
+    ///
+    /// let listener = UnixListener::new("/tmp/hyper.sock").unwrap();
+    /// Server::new(listener).handle(hello).unwrap();
+    /// ```
+    pub struct UnixListener {
+        listener: net::UnixListener,
+        path: PathBuf,
+    }
+
+    impl UnixListener {
+        /// Start listening on a new socket at `path`.
+        ///
+        /// This fails if something already exists at `path`, such as the
+        /// socket of an earlier listener, which isn't removed when the
+        /// listener is dropped.
+        pub fn new<P: AsRef<Path>>(path: P) -> ::Result<UnixListener> {
+            let path = path.as_ref();
+            Ok(UnixListener {
+                listener: try!(net::UnixListener::bind(path)),
+                path: path.to_owned(),
+            })
+        }
+
+        /// The path of the socket.
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+    }
+
+    impl Clone for UnixListener {
+        #[inline]
+        fn clone(&self) -> UnixListener {
+            UnixListener {
+                listener: self.listener.try_clone().unwrap(),
+                path: self.path.clone(),
+            }
+        }
+    }
+
+    impl fmt::Debug for UnixListener {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "UnixListener({:?})", self.path)
+        }
+    }
+
+    impl NetworkListener for UnixListener {
+        type Stream = UnixStream;
+
+        #[inline]
+        fn accept(&mut self) -> ::Result<UnixStream> {
+            Ok(UnixStream(try!(self.listener.accept()).0))
+        }
+
+        /// Unix sockets have no `SocketAddr`, so this fails with an error of
+        /// kind `AddrNotAvailable`.
+        #[inline]
+        fn local_addr(&mut self) -> io::Result<SocketAddr> {
+            Err(no_socket_addr())
+        }
+    }
+
+    /// A stream over a Unix domain socket.
+    pub struct UnixStream(pub net::UnixStream);
+
+    impl Clone for UnixStream {
+        #[inline]
+        fn clone(&self) -> UnixStream {
+            UnixStream(self.0.try_clone().unwrap())
+        }
+    }
+
+    impl fmt::Debug for UnixStream {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("UnixStream(_)")
+        }
+    }
+
+    impl Read for UnixStream {
+        #[inline]
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    impl Write for UnixStream {
+        #[inline]
+        fn write(&mut self, msg: &[u8]) -> io::Result<usize> {
+            self.0.write(msg)
+        }
+        #[inline]
+        fn flush(&mut self) -> io::Result<()> {
+            self.0.flush()
+        }
+    }
+
+    impl NetworkStream for UnixStream {
+        /// Unix sockets have no `SocketAddr`, so this fails with an error of
+        /// kind `AddrNotAvailable`.
+        #[inline]
+        fn peer_addr(&mut self) -> io::Result<SocketAddr> {
+            Err(no_socket_addr())
+        }
+
+        #[inline]
+        fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+            self.0.set_read_timeout(dur)
+        }
+
+        #[inline]
+        fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+            self.0.set_write_timeout(dur)
+        }
+
+        #[inline]
+        fn close(&mut self, how: Shutdown) -> io::Result<()> {
+            match self.0.shutdown(how) {
+                Ok(_) => Ok(()),
+                Err(ref e) if e.kind() == ErrorKind::NotConnected => Ok(()),
+                err => err
+            }
+        }
+
+        fn is_stale(&mut self) -> bool {
+            if self.0.set_nonblocking(true).is_err() {
+                return true;
+            }
+            // `UnixStream` can't peek, but a stream that had anything to
+            // read is stale anyway
+            let stale = match self.0.read(&mut [0]) {
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => false,
+                _ => true
+            };
+            self.0.set_nonblocking(false).is_err() || stale
+        }
+    }
+
+    /// A connector to Unix domain sockets.
+    ///
+    /// The socket is named by the host of the URL, which is the path of the
+    /// socket encoded as hex, so that `http://2f746d702f68797065722e736f636b/status`
+    /// is requested over the socket at `/tmp/hyper.sock`. Such URLs are built
+    /// by `UnixConnector::url`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hyper::Client;
+    /// use hyper::net::UnixConnector;
+    ///
+    /// let client = Client::with_connector(UnixConnector);
+    /// let url = UnixConnector::url("/var/run/docker.sock", "/containers/json").unwrap();
+    /// let res = client.get(url).send().unwrap();
+    /// ```
+    #[derive(Debug, Clone, Default)]
+    pub struct UnixConnector;
+
+    impl UnixConnector {
+        /// The URL of `path` on the server listening on the socket at
+        /// `socket`.
+        pub fn url<P: AsRef<Path>>(socket: P, path: &str) -> Result<Url, ParseError> {
+            let host = socket.as_ref().as_os_str().as_bytes().to_hex();
+            Url::parse(&format!("http://{}{}", host, path))
+        }
+    }
+
+    impl NetworkConnector for UnixConnector {
+        type Stream = UnixStream;
+
+        fn connect(&self, host: &str, _port: u16, scheme: &str) -> ::Result<UnixStream> {
+            if scheme != "http" {
+                return Err(io::Error::new(ErrorKind::InvalidInput,
+                                          "Invalid scheme for Unix").into());
+            }
+            let path = match host.from_hex() {
+                Ok(path) => PathBuf::from(OsStr::from_bytes(&path)),
+                Err(_) => return Err(io::Error::new(ErrorKind::InvalidInput,
+                                                    "Host is not a hex encoded path").into())
+            };
+            debug!("unix socket {:?}", path);
+            Ok(UnixStream(try!(net::UnixStream::connect(path))))
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::io;
+    use std::net::{SocketAddr, TcpListener};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
     use mock::MockStream;
-    use super::{NetworkStream};
+    use super::{HttpConnector, NetworkConnector, NetworkStream, Socks5Addr, Socks5Connector};
+    use super::socks5_handshake;
+    use super::{CachingResolver, ConnectError, Resolve, StaticResolver};
+    #[cfg(unix)]
+    use super::{UnixConnector, UnixListener};
+    use super::{happy_eyeballs, interleave};
 
     #[test]
     fn test_downcast_box_stream() {
@@ -700,5 +1559,304 @@ mod tests {
         let mock = unsafe { stream.downcast_unchecked::<MockStream>() };
         assert_eq!(mock, Box::new(MockStream::new()));
     }
-}
 
+    #[test]
+    fn test_socks5_handshake_domain() {
+        let mut stream = MockStream::with_input(&[5, 0, 5, 0, 0, 1, 10, 0, 0, 1, 0x1f, 0x90]);
+        socks5_handshake(&mut stream, Socks5Addr::Domain("hyper.rs"), 80, None).unwrap();
+        assert_eq!(stream.write, b"\x05\x01\x00\x05\x01\x00\x03\x08hyper.rs\x00\x50".to_vec());
+        assert_eq!(stream.read.position(), 12);
+    }
+
+    #[test]
+    fn test_socks5_handshake_ip_with_credentials() {
+        let mut stream = MockStream::with_input(&[
+            5, 2,
+            1, 0,
+            5, 0, 0, 3, 4, b'h', b'o', b's', b't', 0, 80]);
+        let ip = "127.0.0.1".parse().unwrap();
+        socks5_handshake(&mut stream, Socks5Addr::Ip(ip), 443, Some(("user", "pw"))).unwrap();
+        assert_eq!(stream.write, vec![
+            5, 2, 0, 2,
+            1, 4, b'u', b's', b'e', b'r', 2, b'p', b'w',
+            5, 1, 0, 1, 127, 0, 0, 1, 1, 187]);
+        assert_eq!(stream.read.position(), 15);
+    }
+
+    #[test]
+    fn test_socks5_handshake_auth_failed() {
+        let mut stream = MockStream::with_input(&[5, 2, 1, 1]);
+        let err = socks5_handshake(&mut stream, Socks5Addr::Domain("hyper.rs"), 80,
+                                   Some(("user", "wrong"))).unwrap_err();
+        match err {
+            ::Error::Io(ref e) => assert_eq!(e.kind(), io::ErrorKind::PermissionDenied),
+            e => panic!("unexpected error: {:?}", e)
+        }
+    }
+
+    #[test]
+    fn test_socks5_handshake_refused() {
+        let mut stream = MockStream::with_input(&[5, 0, 5, 5, 0, 1, 0, 0, 0, 0, 0, 0]);
+        let err = socks5_handshake(&mut stream, Socks5Addr::Domain("hyper.rs"), 80, None)
+            .unwrap_err();
+        match err {
+            ::Error::Io(ref e) => assert_eq!(e.kind(), io::ErrorKind::ConnectionRefused),
+            e => panic!("unexpected error: {:?}", e)
+        }
+    }
+
+    #[test]
+    fn test_socks5_handshake_auth_bad_version() {
+        let mut stream = MockStream::with_input(&[5, 2, 5, 0]);
+        let err = socks5_handshake(&mut stream, Socks5Addr::Domain("hyper.rs"), 80,
+                                   Some(("user", "pw"))).unwrap_err();
+        match err {
+            ::Error::Io(ref e) => assert_eq!(e.kind(), io::ErrorKind::InvalidData),
+            e => panic!("unexpected error: {:?}", e)
+        }
+    }
+
+    struct MockSocksServer(&'static [u8]);
+
+    impl NetworkConnector for MockSocksServer {
+        type Stream = MockStream;
+
+        fn connect(&self, host: &str, port: u16, _: &str) -> ::Result<MockStream> {
+            assert_eq!((host, port), ("socks.local", 1080));
+            Ok(MockStream::with_input(self.0))
+        }
+    }
+
+    #[test]
+    fn test_socks5_connector() {
+        let server = MockSocksServer(&[5, 2, 1, 0, 5, 0, 0, 1, 10, 0, 0, 1, 0x1f, 0x90]);
+        let mut socks = Socks5Connector::with_connector(server, "socks.local", 1080);
+        socks.set_credentials("user", "pw");
+        let stream = socks.connect("hyper.rs", 80, "http").unwrap();
+        assert_eq!(stream.write, b"\x05\x02\x00\x02\
+                                   \x01\x04user\x02pw\
+                                   \x05\x01\x00\x03\x08hyper.rs\x00\x50".to_vec());
+        assert!(socks.connect("hyper.rs", 443, "https").is_err());
+
+        let socks = Socks5Connector::with_connector(MockSocksServer(&[5, 0xff]), "socks.local", 1080);
+        assert!(socks.connect("hyper.rs", 80, "http").is_err());
+    }
+
+    #[test]
+    fn test_socks5_no_acceptable_method() {
+        let mut stream = MockStream::with_input(&[5, 0xff]);
+        assert!(socks5_handshake(&mut stream, Socks5Addr::Domain("hyper.rs"), 80, None).is_err());
+    }
+
+    #[test]
+    fn test_http_connector_connect_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let timeout = Some(Duration::from_secs(5));
+        let connector = HttpConnector::new();
+        connector.connect_timeout("127.0.0.1", port, "http", timeout).unwrap();
+        assert!(connector.connect_timeout("127.0.0.1", port, "https", timeout).is_err());
+    }
+
+    #[test]
+    fn test_http_stream_is_stale() {
+        use std::io::Write;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let connector = HttpConnector::new();
+
+        let mut stream = connector.connect("127.0.0.1", port, "http").unwrap();
+        let (mut server, _) = listener.accept().unwrap();
+        assert!(!stream.is_stale());
+        server.write_all(b"unexpected").unwrap();
+        // blocks until the bytes have arrived
+        stream.0.peek(&mut [0]).unwrap();
+        assert!(stream.is_stale());
+
+        let mut stream = connector.connect("127.0.0.1", port, "http").unwrap();
+        let (server, _) = listener.accept().unwrap();
+        assert!(!stream.is_stale());
+        drop(server);
+        // blocks until the close has arrived
+        stream.0.peek(&mut [0]).unwrap();
+        assert!(stream.is_stale());
+    }
+
+    fn closed_addr() -> SocketAddr {
+        // nothing listens on the port once the listener is dropped
+        TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap()
+    }
+
+    #[test]
+    fn test_interleave() {
+        let addrs = vec![
+            "[::1]:80".parse().unwrap(),
+            "[::2]:80".parse().unwrap(),
+            "[::3]:80".parse().unwrap(),
+            "127.0.0.1:80".parse().unwrap(),
+        ];
+        let expected: Vec<SocketAddr> = vec![
+            "[::1]:80".parse().unwrap(),
+            "127.0.0.1:80".parse().unwrap(),
+            "[::2]:80".parse().unwrap(),
+            "[::3]:80".parse().unwrap(),
+        ];
+        assert_eq!(interleave(addrs), expected);
+    }
+
+    #[test]
+    fn test_happy_eyeballs_skips_failed_addrs() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let addrs = vec![closed_addr(), closed_addr(), addr];
+        let stream = happy_eyeballs(addrs, Duration::from_secs(10), None).unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), addr);
+    }
+
+    #[test]
+    fn test_happy_eyeballs_reports_every_failure() {
+        let addrs = vec![closed_addr(), closed_addr()];
+        let err = happy_eyeballs(addrs.clone(), Duration::from_millis(10), None).unwrap_err();
+        let failed = err.errors().iter().map(|&(addr, _)| addr).collect::<Vec<_>>();
+        assert_eq!(failed, addrs);
+        assert!(err.to_string().contains(&addrs[0].to_string()));
+
+        let addr = closed_addr();
+        let err = happy_eyeballs(vec![addr], Duration::from_millis(10), None).unwrap_err();
+        assert_eq!(err.errors().len(), 1);
+        assert_eq!(err.errors()[0].0, addr);
+        assert_eq!(err.errors()[0].1.kind(), io::ErrorKind::ConnectionRefused);
+    }
+
+    #[test]
+    fn test_http_connector_timeout_reports_every_address() {
+        let mut resolver = StaticResolver::new();
+        resolver.add("stand-in.test", "127.0.0.1".parse().unwrap());
+        resolver.add("stand-in.test", "::1".parse().unwrap());
+        let connector = HttpConnector::with_resolver(resolver);
+        // no time at all to connect in
+        let timeout = Some(Duration::from_secs(0));
+        match connector.connect_timeout("stand-in.test", 80, "http", timeout) {
+            Err(::Error::Io(ref e)) => {
+                assert_eq!(e.kind(), io::ErrorKind::TimedOut);
+                let err = e.get_ref().unwrap().downcast_ref::<ConnectError>().unwrap();
+                assert_eq!(err.errors().len(), 2);
+            },
+            other => panic!("expected Io error, got {:?}", other.map(|_| ()))
+        }
+    }
+
+    #[test]
+    fn test_static_resolver() {
+        let fallback = |host: &str, port: u16| -> io::Result<Vec<SocketAddr>> {
+            assert_eq!(host, "other.domain");
+            Ok(vec![SocketAddr::new("10.0.0.1".parse().unwrap(), port)])
+        };
+        let mut resolver = StaticResolver::with_fallback(fallback);
+        resolver.add("Example.Domain", "127.0.0.1".parse().unwrap());
+        resolver.add("example.domain", "::1".parse().unwrap());
+        assert_eq!(resolver.resolve("example.DOMAIN", 8080).unwrap(), vec![
+            "127.0.0.1:8080".parse().unwrap(),
+            "[::1]:8080".parse().unwrap(),
+        ]);
+        assert_eq!(resolver.resolve("other.domain", 80).unwrap(),
+                   vec!["10.0.0.1:80".parse().unwrap()]);
+    }
+
+    #[test]
+    fn test_caching_resolver() {
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let counter = lookups.clone();
+        let resolver = move |_: &str, port: u16| -> io::Result<Vec<SocketAddr>> {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![SocketAddr::new("127.0.0.1".parse().unwrap(), port)])
+        };
+
+        let cache = CachingResolver::with_resolver(resolver.clone(), Duration::from_secs(60));
+        assert_eq!(cache.resolve("example.domain", 80).unwrap(),
+                   vec!["127.0.0.1:80".parse().unwrap()]);
+        assert_eq!(cache.resolve("example.domain", 443).unwrap(),
+                   vec!["127.0.0.1:443".parse().unwrap()]);
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+        cache.clear();
+        cache.resolve("example.domain", 80).unwrap();
+        assert_eq!(lookups.load(Ordering::SeqCst), 2);
+
+        let expired = CachingResolver::with_resolver(resolver, Duration::from_secs(0));
+        expired.resolve("example.domain", 80).unwrap();
+        expired.resolve("example.domain", 80).unwrap();
+        assert_eq!(lookups.load(Ordering::SeqCst), 4);
+        expired.resolve("other.domain", 80).unwrap();
+        assert_eq!(expired.cache.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_caching_resolver_clones_share_cache() {
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let counter = lookups.clone();
+        let resolver = move |_: &str, port: u16| -> io::Result<Vec<SocketAddr>> {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![SocketAddr::new("127.0.0.1".parse().unwrap(), port)])
+        };
+
+        let connector = HttpConnector::with_resolver(
+            CachingResolver::with_resolver(resolver, Duration::from_secs(60)));
+        connector.resolver().resolve("example.domain", 80).unwrap();
+        connector.clone().resolver().resolve("example.domain", 80).unwrap();
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_http_connector_with_resolver() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut resolver = StaticResolver::new();
+        resolver.add("stand-in.test", addr.ip());
+        let connector = HttpConnector::with_resolver(resolver);
+        let mut stream = connector.connect("stand-in.test", addr.port(), "http").unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), addr);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unix_connector_url() {
+        let url = UnixConnector::url("/tmp/hyper.sock", "/status?verbose").unwrap();
+        assert_eq!(url.serialize(), "http://2f746d702f68797065722e736f636b/status?verbose");
+        let err = UnixConnector.connect("not-hex", 80, "http").unwrap_err();
+        match err {
+            ::Error::Io(ref e) => assert_eq!(e.kind(), io::ErrorKind::InvalidInput),
+            e => panic!("unexpected error: {:?}", e)
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unix_server_and_client() {
+        use std::env;
+        use std::fs;
+        use std::io::Read;
+        use std::process;
+        use client::{Client, Pool};
+        use server::{Request, Response, Server};
+
+        let path = env::temp_dir().join(format!("hyper-test-{}.sock", process::id()));
+        let _ = fs::remove_file(&path);
+        let mut server = Server::new(UnixListener::new(&path).unwrap());
+        server.keep_alive(Duration::from_secs(5));
+        let mut listening = server.handle_threads(|req: Request, res: Response| {
+            res.send(req.remote_addr.to_string().as_bytes()).unwrap();
+        }, 1).unwrap();
+
+        let client = Client::with_connector(Pool::with_connector(Default::default(), UnixConnector));
+        for _ in 0..2 {
+            let url = UnixConnector::url(&path, "/").unwrap();
+            let mut res = client.get(url).send().unwrap();
+            let mut body = String::new();
+            res.read_to_string(&mut body).unwrap();
+            assert_eq!(body, "0.0.0.0:0");
+        }
+        listening.close().unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -109,7 +109,7 @@
 //! implement `Write`.
 use std::fmt;
 use std::io::{self, ErrorKind, BufWriter, Write};
-use std::net::{SocketAddr, ToSocketAddrs};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs};
 use std::thread::{self, JoinHandle};
 use std::time::Duration;
 
@@ -132,6 +132,7 @@ use version::HttpVersion::Http11;
 
 use self::listener::ListenerPool;
 
+pub mod multipart;
 pub mod request;
 pub mod response;
 
@@ -177,20 +178,15 @@ impl<L: NetworkListener> Server<L> {
     ///
     /// The timeout duration passed will be used to determine how long
     /// to keep the connection alive before dropping it.
-    ///
-    /// **NOTE**: The timeout will only be used when the `timeouts` feature
-    /// is enabled for hyper, and rustc is 1.4 or greater.
     #[inline]
     pub fn keep_alive(&mut self, timeout: Duration) {
         self.timeouts.keep_alive = Some(timeout);
     }
 
-    #[cfg(feature = "timeouts")]
     pub fn set_read_timeout(&mut self, dur: Option<Duration>) {
         self.timeouts.read = dur;
     }
 
-    #[cfg(feature = "timeouts")]
     pub fn set_write_timeout(&mut self, dur: Option<Duration>) {
         self.timeouts.write = dur;
     }
@@ -228,7 +224,11 @@ impl<L: NetworkListener + Send + 'static> Server<L> {
 
 fn handle<H, L>(mut server: Server<L>, handler: H, threads: usize) -> ::Result<Listening>
 where H: Handler + 'static, L: NetworkListener + Send + 'static {
-    let socket = try!(server.listener.local_addr());
+    let socket = match server.listener.local_addr() {
+        Ok(addr) => addr,
+        Err(ref e) if e.kind() == ErrorKind::AddrNotAvailable => unspecified_addr(),
+        Err(e) => return Err(e.into())
+    };
 
     debug!("threads = {:?}", threads);
     let pool = ListenerPool::new(server.listener);
@@ -243,6 +243,12 @@ where H: Handler + 'static, L: NetworkListener + Send + 'static {
     })
 }
 
+/// The address used for streams and listeners that have no `SocketAddr`,
+/// such as those of Unix sockets.
+fn unspecified_addr() -> SocketAddr {
+    SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 0)
+}
+
 struct Worker<H: Handler + 'static> {
     handler: H,
     timeouts: Timeouts,
@@ -268,6 +274,7 @@ impl<H: Handler + 'static> Worker<H> {
 
         let addr = match stream.peer_addr() {
             Ok(addr) => addr,
+            Err(ref e) if e.kind() == ErrorKind::AddrNotAvailable => unspecified_addr(),
             Err(e) => {
                 error!("Peer Name error: {:?}", e);
                 return;
@@ -296,22 +303,10 @@ impl<H: Handler + 'static> Worker<H> {
         self.set_write_timeout(s, self.timeouts.write)
     }
 
-    #[cfg(not(feature = "timeouts"))]
-    fn set_write_timeout(&self, _s: &NetworkStream, _timeout: Option<Duration>) -> io::Result<()> {
-        Ok(())
-    }
-
-    #[cfg(feature = "timeouts")]
     fn set_write_timeout(&self, s: &NetworkStream, timeout: Option<Duration>) -> io::Result<()> {
         s.set_write_timeout(timeout)
     }
 
-    #[cfg(not(feature = "timeouts"))]
-    fn set_read_timeout(&self, _s: &NetworkStream, _timeout: Option<Duration>) -> io::Result<()> {
-        Ok(())
-    }
-
-    #[cfg(feature = "timeouts")]
     fn set_read_timeout(&self, s: &NetworkStream, timeout: Option<Duration>) -> io::Result<()> {
         s.set_read_timeout(timeout)
     }
@@ -392,6 +387,8 @@ impl<H: Handler + 'static> Worker<H> {
 pub struct Listening {
     _guard: Option<JoinHandle<()>>,
     /// The socket addresses that the server is bound to.
+    ///
+    /// It is `0.0.0.0:0` for listeners without one, such as a `UnixListener`.
     pub socket: SocketAddr,
 }
 
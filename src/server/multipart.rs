@@ -0,0 +1,549 @@
+//! Multipart form bodies.
+//!
+//! A `multipart/form-data` request body is parsed as it is read, one part at
+//! a time, so file uploads are never buffered in memory.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::io;
+//! use hyper::server::{Request, Response};
+//! use hyper::server::multipart::Multipart;
+//! use hyper::status::StatusCode;
+//!
+//! fn upload(mut req: Request, mut res: Response) {
+//!     let mut form = match Multipart::from_request(&mut req) {
+//!         Ok(form) => form,
+//!         Err(_) => {
+//!             *res.status_mut() = StatusCode::BadRequest;
+//!             return;
+//!         }
+//!     };
+//!     while let Some(mut part) = form.next_part().unwrap() {
+//!         println!("{:?} ({:?})", part.name(), part.file_name());
+//!         io::copy(&mut part, &mut io::sink()).unwrap();
+//!     }
+//! }
+//! ```
+use std::cmp;
+use std::fmt;
+use std::io::{self, Read};
+
+use httparse;
+use mime::{Attr, Mime, SubLevel, TopLevel};
+
+use header::{ContentType, Headers};
+use server::Request;
+use Error;
+
+const MAX_PART_HEADERS: usize = 16;
+const MAX_HEAD_SIZE: usize = 8 * 1024;
+const READ_SIZE: usize = 8 * 1024;
+
+/// Limits on the parts of a `Multipart` body.
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    /// The maximum number of parts.
+    pub max_parts: usize,
+    /// The maximum size, in bytes, of the contents of a part.
+    pub max_part_size: u64,
+}
+
+impl Default for Limits {
+    /// Allows 100 parts of up to 64MB each.
+    fn default() -> Limits {
+        Limits {
+            max_parts: 100,
+            max_part_size: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// A streaming parser of a `multipart/form-data` body.
+pub struct Multipart<R> {
+    reader: R,
+    delimiter: Vec<u8>,
+    buf: Vec<u8>,
+    pos: usize,
+    limits: Limits,
+    parts: usize,
+    state: State,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    /// Reading the preamble or the contents of a part.
+    Body,
+    /// Just after a boundary delimiter.
+    Boundary,
+    /// After the closing delimiter.
+    Done,
+}
+
+impl<'r, 'a: 'r, 'b: 'a> Multipart<&'r mut Request<'a, 'b>> {
+    /// Parse the body of a request, using the boundary of its `Content-Type`.
+    ///
+    /// Returns `Error::Header` if the request is not `multipart/form-data`,
+    /// or has no boundary.
+    pub fn from_request(request: &'r mut Request<'a, 'b>)
+            -> ::Result<Multipart<&'r mut Request<'a, 'b>>> {
+        let boundary = match request.headers.get::<ContentType>() {
+            Some(&ContentType(ref mime @ Mime(TopLevel::Multipart, SubLevel::FormData, _))) => {
+                mime.get_param(Attr::Boundary).map(|boundary| boundary.to_string())
+            },
+            _ => None
+        };
+        match boundary {
+            Some(boundary) => Ok(Multipart::new(request, &boundary)),
+            None => Err(Error::Header)
+        }
+    }
+}
+
+impl<R: Read> Multipart<R> {
+    /// Parse a body read from `reader`, whose parts are separated by
+    /// `boundary`.
+    pub fn new(reader: R, boundary: &str) -> Multipart<R> {
+        Multipart {
+            reader: reader,
+            delimiter: format!("\r\n--{}", boundary).into_bytes(),
+            // the first delimiter doesn't follow a line break, unless there
+            // is a preamble, so pretend there is one
+            buf: b"\r\n".to_vec(),
+            pos: 0,
+            limits: Limits::default(),
+            parts: 0,
+            state: State::Body,
+        }
+    }
+
+    /// Set the limits on the parts of this body.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// The next part of the body, or `None` after the last one.
+    ///
+    /// Any of the previous part that wasn't read is skipped.
+    pub fn next_part(&mut self) -> ::Result<Option<Part<R>>> {
+        let mut skip = [0; 1024];
+        while try!(self.read_body(&mut skip)) > 0 {}
+
+        if self.state == State::Done {
+            return Ok(None);
+        }
+        // the delimiter is followed by "--" after the last part, and
+        // otherwise by optional whitespace and a line break
+        let line = try!(self.read_line());
+        if line.starts_with(b"--") {
+            self.state = State::Done;
+            return Ok(None);
+        }
+        if !line.iter().all(|&b| b == b' ' || b == b'\t') {
+            return Err(Error::Io(invalid("invalid multipart boundary")));
+        }
+
+        self.parts += 1;
+        if self.parts > self.limits.max_parts {
+            return Err(Error::Io(invalid("too many multipart parts")));
+        }
+        let headers = try!(self.read_headers());
+        self.state = State::Body;
+
+        let (name, file_name) = match headers.get_raw("Content-Disposition") {
+            Some(raw) if raw.len() == 1 => {
+                let params = disposition_params(&String::from_utf8_lossy(&raw[0]));
+                let param = |name: &str| params.iter()
+                    .find(|&&(ref key, _)| key.eq_ignore_ascii_case(name))
+                    .map(|&(_, ref value)| value.clone());
+                (param("name"), param("filename"))
+            },
+            _ => (None, None)
+        };
+        Ok(Some(Part {
+            headers: headers,
+            name: name,
+            file_name: file_name,
+            read: 0,
+            multipart: self,
+        }))
+    }
+
+    /// Read the contents of the current part, up to the next delimiter.
+    fn read_body(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.state != State::Body {
+            return Ok(0);
+        }
+        loop {
+            let found = find(&self.buf[self.pos..], &self.delimiter);
+            let available = self.buf.len() - self.pos;
+            match found {
+                Some(0) => {
+                    self.pos += self.delimiter.len();
+                    self.state = State::Boundary;
+                    return Ok(0);
+                },
+                Some(i) => return Ok(self.consume(out, i)),
+                None => {
+                    // the end of the buffer could be the start of a delimiter
+                    let safe = available.saturating_sub(self.delimiter.len() - 1);
+                    if safe > 0 {
+                        return Ok(self.consume(out, safe));
+                    }
+                    if try!(self.fill()) == 0 {
+                        return Err(eof());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Read the rest of the line after a delimiter.
+    fn read_line(&mut self) -> io::Result<Vec<u8>> {
+        loop {
+            if let Some(i) = find(&self.buf[self.pos..], b"\r\n") {
+                let line = self.buf[self.pos..self.pos + i].to_vec();
+                self.pos += i + 2;
+                return Ok(line);
+            }
+            // the closing delimiter doesn't need a line break after it
+            if self.buf[self.pos..].starts_with(b"--") {
+                self.pos += 2;
+                return Ok(b"--".to_vec());
+            }
+            if self.buf.len() - self.pos > MAX_HEAD_SIZE {
+                return Err(invalid("invalid multipart boundary"));
+            }
+            if try!(self.fill()) == 0 {
+                return Err(eof());
+            }
+        }
+    }
+
+    fn read_headers(&mut self) -> ::Result<Headers> {
+        loop {
+            let parsed = {
+                let mut raw = [httparse::EMPTY_HEADER; MAX_PART_HEADERS];
+                match try!(httparse::parse_headers(&self.buf[self.pos..], &mut raw)) {
+                    httparse::Status::Complete((len, raw)) => {
+                        Some((len, try!(Headers::from_raw(raw))))
+                    },
+                    httparse::Status::Partial => None
+                }
+            };
+            match parsed {
+                Some((len, headers)) => {
+                    self.pos += len;
+                    return Ok(headers);
+                },
+                None => {
+                    if self.buf.len() - self.pos > MAX_HEAD_SIZE {
+                        return Err(Error::TooLarge);
+                    }
+                    if try!(self.fill()) == 0 {
+                        return Err(Error::Io(eof()));
+                    }
+                }
+            }
+        }
+    }
+
+    fn consume(&mut self, out: &mut [u8], len: usize) -> usize {
+        let n = cmp::min(len, out.len());
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        n
+    }
+
+    /// Read more of the body into the buffer, after what is unconsumed.
+    fn fill(&mut self) -> io::Result<usize> {
+        if self.pos > 0 {
+            self.buf.drain(..self.pos);
+            self.pos = 0;
+        }
+        let len = self.buf.len();
+        self.buf.resize(len + READ_SIZE, 0);
+        let res = self.reader.read(&mut self.buf[len..]);
+        let n = *res.as_ref().unwrap_or(&0);
+        self.buf.truncate(len + n);
+        res
+    }
+}
+
+impl<R> fmt::Debug for Multipart<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Multipart")
+            .field("limits", &self.limits)
+            .field("parts", &self.parts)
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+/// A part of a `Multipart` body, which reads its contents.
+pub struct Part<'m, R: 'm> {
+    /// The headers of this part.
+    pub headers: Headers,
+    name: Option<String>,
+    file_name: Option<String>,
+    read: u64,
+    multipart: &'m mut Multipart<R>,
+}
+
+impl<'m, R: Read> Part<'m, R> {
+    /// The name of the form field, from the `Content-Disposition` header.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(|s| &s[..])
+    }
+
+    /// The name of the uploaded file, if this part is a file.
+    pub fn file_name(&self) -> Option<&str> {
+        self.file_name.as_ref().map(|s| &s[..])
+    }
+
+    /// The `Content-Type` of this part, if it has one.
+    pub fn content_type(&self) -> Option<&Mime> {
+        self.headers.get::<ContentType>().map(|&ContentType(ref mime)| mime)
+    }
+}
+
+impl<'m, R: Read> Read for Part<'m, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = try!(self.multipart.read_body(buf));
+        self.read += n as u64;
+        if self.read > self.multipart.limits.max_part_size {
+            return Err(invalid("multipart part is too large"));
+        }
+        Ok(n)
+    }
+}
+
+impl<'m, R> fmt::Debug for Part<'m, R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Part")
+            .field("headers", &self.headers)
+            .field("name", &self.name)
+            .field("file_name", &self.file_name)
+            .finish()
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+fn invalid(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn eof() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "multipart body ended early")
+}
+
+/// The parameters of a `Content-Disposition` value, such as
+/// `form-data; name="upload"; filename="a.txt"`.
+fn disposition_params(value: &str) -> Vec<(String, String)> {
+    let mut params = vec![];
+    let mut chars = value.chars().skip_while(|&c| c != ';').peekable();
+    while chars.next().is_some() {
+        let key: String = chars.by_ref().take_while(|&c| c != '=').collect();
+        while chars.peek() == Some(&' ') {
+            chars.next();
+        }
+        let mut value = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' => value.extend(chars.next()),
+                    c => value.push(c)
+                }
+            }
+            // skip to the next parameter
+            while chars.peek().map_or(false, |&c| c != ';') {
+                chars.next();
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c == ';' {
+                    break;
+                }
+                value.push(c);
+                chars.next();
+            }
+        }
+        params.push((key.trim().to_owned(), value.trim().to_owned()));
+    }
+    params
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read};
+    use mime::{Mime, TopLevel, SubLevel};
+
+    use buffer::BufReader;
+    use mock::MockStream;
+    use net::NetworkStream;
+    use server::Request;
+    use Error;
+    use super::{Limits, Multipart, disposition_params};
+
+    const BODY: &'static [u8] = b"preamble\r\n\
+        --XyZ\r\n\
+        Content-Disposition: form-data; name=\"version\"\r\n\
+        \r\n\
+        1.0.3\r\n\
+        --XyZ  \r\n\
+        Content-Disposition: form-data; name=\"upload\"; filename=\"a \\\"b\\\".txt\"\r\n\
+        Content-Type: text/plain\r\n\
+        \r\n\
+        line one\r\n--XyYnot a boundary\r\n\
+        --XyZ--\r\n\
+        epilogue";
+
+    /// Reads one byte at a time, so that delimiters are split across reads.
+    struct Trickle<R>(R);
+
+    impl<R: Read> Read for Trickle<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = if buf.is_empty() { 0 } else { 1 };
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    fn parts<R: Read>(mut form: Multipart<R>) -> ::Result<Vec<(Option<String>, String)>> {
+        let mut parts = vec![];
+        while let Some(mut part) = try!(form.next_part()) {
+            let mut body = String::new();
+            try!(part.read_to_string(&mut body));
+            parts.push((part.name().map(|s| s.to_owned()), body));
+        }
+        Ok(parts)
+    }
+
+    #[test]
+    fn test_multipart_parts() {
+        let mut form = Multipart::new(BODY, "XyZ");
+        {
+            let mut part = form.next_part().unwrap().unwrap();
+            assert_eq!(part.name(), Some("version"));
+            assert_eq!(part.file_name(), None);
+            assert_eq!(part.content_type(), None);
+            let mut s = String::new();
+            part.read_to_string(&mut s).unwrap();
+            assert_eq!(s, "1.0.3");
+        }
+        {
+            let mut part = form.next_part().unwrap().unwrap();
+            assert_eq!(part.name(), Some("upload"));
+            assert_eq!(part.file_name(), Some("a \"b\".txt"));
+            assert_eq!(part.content_type(), Some(&Mime(TopLevel::Text, SubLevel::Plain, vec![])));
+            let mut s = String::new();
+            part.read_to_string(&mut s).unwrap();
+            assert_eq!(s, "line one\r\n--XyYnot a boundary");
+        }
+        assert!(form.next_part().unwrap().is_none());
+        assert!(form.next_part().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_multipart_trickle() {
+        let parts = parts(Multipart::new(Trickle(BODY), "XyZ")).unwrap();
+        assert_eq!(parts, vec![
+            (Some("version".to_owned()), "1.0.3".to_owned()),
+            (Some("upload".to_owned()), "line one\r\n--XyYnot a boundary".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn test_multipart_skip_unread_part() {
+        let mut form = Multipart::new(BODY, "XyZ");
+        form.next_part().unwrap().unwrap();
+        let part = form.next_part().unwrap().unwrap();
+        assert_eq!(part.name(), Some("upload"));
+    }
+
+    #[test]
+    fn test_multipart_max_parts() {
+        let mut form = Multipart::new(BODY, "XyZ");
+        form.set_limits(Limits { max_parts: 1, ..Limits::default() });
+        assert!(form.next_part().unwrap().is_some());
+        match form.next_part() {
+            Err(Error::Io(ref e)) if e.kind() == io::ErrorKind::InvalidData => (),
+            other => panic!("expected InvalidData, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_multipart_max_part_size() {
+        let mut form = Multipart::new(BODY, "XyZ");
+        form.set_limits(Limits { max_part_size: 4, ..Limits::default() });
+        let mut part = form.next_part().unwrap().unwrap();
+        let mut s = String::new();
+        let err = part.read_to_string(&mut s).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_multipart_unexpected_eof() {
+        let body = b"--XyZ\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\ntrunc";
+        match parts(Multipart::new(&body[..], "XyZ")) {
+            Err(Error::Io(ref e)) if e.kind() == io::ErrorKind::UnexpectedEof => (),
+            other => panic!("expected UnexpectedEof, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_multipart_from_request() {
+        let mut mock = MockStream::with_input(b"\
+            POST /upload HTTP/1.1\r\n\
+            Host: example.domain\r\n\
+            Content-Type: multipart/form-data; boundary=XyZ\r\n\
+            Content-Length: 61\r\n\
+            \r\n\
+            --XyZ\r\n\
+            Content-Disposition: form-data; name=\"a\"\r\n\
+            \r\n\
+            1\r\n\
+            --XyZ--");
+
+        // FIXME: Use Type ascription
+        let mock: &mut NetworkStream = &mut mock;
+        let mut stream = BufReader::new(mock);
+
+        let mut req = Request::new(&mut stream, "127.0.0.1:80".parse().unwrap()).unwrap();
+        let parts = parts(Multipart::from_request(&mut req).unwrap()).unwrap();
+        assert_eq!(parts, vec![(Some("a".to_owned()), "1".to_owned())]);
+    }
+
+    #[test]
+    fn test_multipart_from_request_not_multipart() {
+        let mut mock = MockStream::with_input(b"\
+            POST /upload HTTP/1.1\r\n\
+            Host: example.domain\r\n\
+            Content-Type: text/plain\r\n\
+            Content-Length: 0\r\n\
+            \r\n");
+
+        // FIXME: Use Type ascription
+        let mock: &mut NetworkStream = &mut mock;
+        let mut stream = BufReader::new(mock);
+
+        let mut req = Request::new(&mut stream, "127.0.0.1:80".parse().unwrap()).unwrap();
+        match Multipart::from_request(&mut req) {
+            Err(Error::Header) => (),
+            other => panic!("expected Error::Header, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_disposition_params() {
+        assert_eq!(disposition_params("form-data; name=a; filename=\"b;c.txt\""), vec![
+            ("name".to_owned(), "a".to_owned()),
+            ("filename".to_owned(), "b;c.txt".to_owned()),
+        ]);
+        assert_eq!(disposition_params("form-data"), vec![]);
+    }
+}
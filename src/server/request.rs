@@ -6,18 +6,28 @@ use std::io::{self, Read};
 use std::net::SocketAddr;
 use std::time::Duration;
 
+use mime::{Mime, TopLevel, SubLevel};
+use url::form_urlencoded;
+
 use buffer::BufReader;
 use net::NetworkStream;
 use version::{HttpVersion};
 use method::Method::{self, Get, Head};
-use header::{Headers, ContentLength, TransferEncoding};
+use header::{Headers, ContentLength, ContentType, TransferEncoding};
 use http::h1::{self, Incoming, HttpReader};
 use http::h1::HttpReader::{SizedReader, ChunkedReader, EmptyReader};
 use uri::RequestUri;
 
+/// The longest `application/x-www-form-urlencoded` body that `read_form`
+/// reads, 2MB.
+pub const MAX_FORM_SIZE: u64 = 2 * 1024 * 1024;
+
 /// A request bundles several parts of an incoming `NetworkStream`, given to a `Handler`.
 pub struct Request<'a, 'b: 'a> {
     /// The IP address of the remote connection.
+    ///
+    /// It is `0.0.0.0:0` for connections without one, such as over a Unix
+    /// socket.
     pub remote_addr: SocketAddr,
     /// The `Method`, such as `Get`, `Post`, etc.
     pub method: Method,
@@ -65,19 +75,30 @@ impl<'a, 'b: 'a> Request<'a, 'b> {
         })
     }
 
+    /// Read an `application/x-www-form-urlencoded` body, and return its
+    /// percent-decoded pairs.
+    ///
+    /// Returns `Error::Header` if the request has another `Content-Type`,
+    /// and `Error::TooLarge` if the body is longer than `MAX_FORM_SIZE`.
+    pub fn read_form(&mut self) -> ::Result<Vec<(String, String)>> {
+        match self.headers.get::<ContentType>() {
+            Some(&ContentType(Mime(TopLevel::Application, SubLevel::WwwFormUrlEncoded, _))) => (),
+            _ => return Err(::Error::Header)
+        }
+        let mut body = Vec::new();
+        try!(self.by_ref().take(MAX_FORM_SIZE + 1).read_to_end(&mut body));
+        if body.len() as u64 > MAX_FORM_SIZE {
+            return Err(::Error::TooLarge);
+        }
+        Ok(form_urlencoded::parse(&body))
+    }
+
     /// Set the read timeout of the underlying NetworkStream.
-    #[cfg(feature = "timeouts")]
     #[inline]
     pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
         self.body.get_ref().get_ref().set_read_timeout(timeout)
     }
 
-    /// Set the read timeout of the underlying NetworkStream.
-    #[cfg(not(feature = "timeouts"))]
-    #[inline]
-    pub fn set_read_timeout(&self, _timeout: Option<Duration>) -> io::Result<()> {
-        Ok(())
-    }
     /// Get a reference to the underlying `NetworkStream`.
     #[inline]
     pub fn downcast_ref<T: NetworkStream>(&self) -> Option<&T> {
@@ -313,4 +334,47 @@ mod tests {
         assert_eq!(read_to_string(req).unwrap(), "1".to_owned());
     }
 
+    #[test]
+    fn test_read_form() {
+        let mut mock = MockStream::with_input(b"\
+            POST / HTTP/1.1\r\n\
+            Host: example.domain\r\n\
+            Content-Type: application/x-www-form-urlencoded\r\n\
+            Content-Length: 19\r\n\
+            \r\n\
+            a=1&b=two+words%21\n"
+        );
+
+        // FIXME: Use Type ascription
+        let mock: &mut NetworkStream = &mut mock;
+        let mut stream = BufReader::new(mock);
+
+        let mut req = Request::new(&mut stream, sock("127.0.0.1:80")).unwrap();
+        assert_eq!(req.read_form().unwrap(), vec![
+            ("a".to_owned(), "1".to_owned()),
+            ("b".to_owned(), "two words!\n".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn test_read_form_wrong_content_type() {
+        let mut mock = MockStream::with_input(b"\
+            POST / HTTP/1.1\r\n\
+            Host: example.domain\r\n\
+            Content-Type: text/plain\r\n\
+            Content-Length: 3\r\n\
+            \r\n\
+            a=1"
+        );
+
+        // FIXME: Use Type ascription
+        let mock: &mut NetworkStream = &mut mock;
+        let mut stream = BufReader::new(mock);
+
+        let mut req = Request::new(&mut stream, sock("127.0.0.1:80")).unwrap();
+        match req.read_form() {
+            Err(::Error::Header) => (),
+            other => panic!("expected Error::Header, got {:?}", other)
+        }
+    }
 }
@@ -3,14 +3,20 @@
 //! These are responses sent by a `hyper::Server` to clients, after
 //! receiving a request.
 use std::any::{Any, TypeId};
+use std::fmt;
 use std::marker::PhantomData;
 use std::mem;
 use std::io::{self, Write};
 use std::ptr;
 
+use flate2::Compression;
+use flate2::write::{GzEncoder, ZlibEncoder};
 use time::now_utc;
+use unicase::UniCase;
 
 use header;
+use header::{AcceptEncoding, ContentEncoding, ContentLength, ContentType, Encoding, Vary};
+use mime::{Mime, TopLevel, SubLevel};
 use http::h1::{CR, LF, LINE_ENDING, HttpWriter};
 use http::h1::HttpWriter::{ThroughWriter, ChunkedWriter, SizedWriter, EmptyWriter};
 use status;
@@ -193,6 +199,86 @@ impl<'a> Response<'a, Fresh> {
             _writing: PhantomData,
         })
     }
+
+    /// Writes the body compressed with the best encoding the request accepts,
+    /// and ends the response.
+    ///
+    /// This is the compressing counterpart of `send`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use hyper::server::{Request, Response};
+    /// use hyper::header::AcceptEncoding;
+    /// fn handler(req: Request, res: Response) {
+    ///     res.send_compressed(req.headers.get::<AcceptEncoding>(), b"Hello World!").unwrap();
+    /// }
+    /// ```
+    pub fn send_compressed(self, accept: Option<&AcceptEncoding>, body: &[u8])
+            -> io::Result<()> {
+        self.headers.set(ContentLength(body.len() as u64));
+        let mut stream = try!(self.start_compressed(accept));
+        try!(stream.write_all(body));
+        stream.end()
+    }
+
+    /// Consume this Response<Fresh>, writing the Headers and Status and
+    /// creating a `CompressedResponse`, which encodes the body with the best
+    /// encoding the request accepts.
+    ///
+    /// The body is compressed with `gzip` or `deflate`, whichever the request's
+    /// `Accept-Encoding` prefers. It is left alone if the request doesn't
+    /// accept either, if the response already has a `Content-Encoding`, if
+    /// its `Content-Type` is already compressed, such as images, or if its
+    /// `Content-Length` is too small to benefit.
+    ///
+    /// When compressing, the `Content-Length` is removed, since the body will
+    /// be chunked. `Vary: Accept-Encoding` is set whenever the encoding could
+    /// have depended on the request.
+    pub fn start_compressed(self, accept: Option<&AcceptEncoding>)
+            -> io::Result<CompressedResponse<'a>> {
+        let encoding = if self.is_compressible() {
+            vary_accept_encoding(self.headers);
+            accept.and_then(negotiate_encoding)
+        } else {
+            None
+        };
+        if let Some(ref encoding) = encoding {
+            debug!("compressing response with {:?}", encoding);
+            self.headers.remove::<ContentLength>();
+            self.headers.set(ContentEncoding(vec![encoding.clone()]));
+        }
+        let res = try!(self.start());
+        Ok(CompressedResponse {
+            encoder: match encoding {
+                Some(Encoding::Gzip) => Encoder::Gzip(GzEncoder::new(res, Compression::Default)),
+                Some(Encoding::Deflate) => {
+                    Encoder::Deflate(ZlibEncoder::new(res, Compression::Default))
+                },
+                _ => Encoder::Identity(res)
+            }
+        })
+    }
+
+    fn is_compressible(&self) -> bool {
+        match self.status {
+            status::StatusCode::NoContent | status::StatusCode::NotModified => return false,
+            c if c.class() == status::StatusClass::Informational => return false,
+            _ => ()
+        }
+        if self.headers.has::<ContentEncoding>() {
+            return false;
+        }
+        if let Some(&ContentLength(len)) = self.headers.get() {
+            if len < MIN_COMPRESS_LENGTH {
+                return false;
+            }
+        }
+        match self.headers.get::<ContentType>() {
+            Some(&ContentType(ref mime)) => !is_compressed_mime(mime),
+            None => true
+        }
+    }
     /// Get a mutable reference to the status.
     #[inline]
     pub fn status_mut(&mut self) -> &mut status::StatusCode { &mut self.status }
@@ -227,6 +313,122 @@ impl<'a> Write for Response<'a, Streaming> {
     }
 }
 
+/// Bodies shorter than this are not worth compressing.
+const MIN_COMPRESS_LENGTH: u64 = 860;
+
+/// Whether a media type is already compressed, or should not be buffered by
+/// a compressor.
+fn is_compressed_mime(mime: &Mime) -> bool {
+    match *mime {
+        Mime(TopLevel::Image, SubLevel::Ext(ref sub), _) => sub != "svg+xml",
+        Mime(TopLevel::Image, _, _) |
+        Mime(TopLevel::Audio, _, _) |
+        Mime(TopLevel::Video, _, _) |
+        Mime(TopLevel::Text, SubLevel::EventStream, _) => true,
+        Mime(TopLevel::Application, SubLevel::Ext(ref sub), _) => match &sub[..] {
+            "zip" | "gzip" | "x-gzip" | "x-bzip2" | "x-xz" | "x-7z-compressed" |
+            "x-rar-compressed" => true,
+            _ => false
+        },
+        _ => false
+    }
+}
+
+/// Picks the encoding to compress with, out of those the request accepts.
+///
+/// `gzip` is preferred over `deflate` when both are accepted equally.
+fn negotiate_encoding(accept: &AcceptEncoding) -> Option<Encoding> {
+    let quality = |encoding: &Encoding| {
+        accept.iter().find(|q| q.item == *encoding)
+            .or_else(|| accept.iter().find(|q| q.item == Encoding::EncodingExt("*".to_owned())))
+            .map_or(0, |q| q.quality.0)
+    };
+    let gzip = quality(&Encoding::Gzip);
+    let deflate = quality(&Encoding::Deflate);
+    if gzip == 0 && deflate == 0 {
+        None
+    } else if gzip >= deflate {
+        Some(Encoding::Gzip)
+    } else {
+        Some(Encoding::Deflate)
+    }
+}
+
+fn vary_accept_encoding(headers: &mut header::Headers) {
+    let accept_encoding = UniCase("Accept-Encoding".to_owned());
+    match headers.get_mut::<Vary>() {
+        Some(&mut Vary::Any) => return,
+        Some(&mut Vary::Items(ref mut items)) => {
+            if !items.contains(&accept_encoding) {
+                items.push(accept_encoding);
+            }
+            return;
+        },
+        None => ()
+    }
+    headers.set(Vary::Items(vec![accept_encoding]));
+}
+
+/// A streaming Response, whose body is compressed as it is written.
+///
+/// Created by `Response::start_compressed`.
+pub struct CompressedResponse<'a> {
+    encoder: Encoder<'a>,
+}
+
+enum Encoder<'a> {
+    Identity(Response<'a, Streaming>),
+    Gzip(GzEncoder<Response<'a, Streaming>>),
+    Deflate(ZlibEncoder<Response<'a, Streaming>>),
+}
+
+impl<'a> CompressedResponse<'a> {
+    /// The encoding the body is compressed with, if any.
+    pub fn encoding(&self) -> Option<Encoding> {
+        match self.encoder {
+            Encoder::Identity(..) => None,
+            Encoder::Gzip(..) => Some(Encoding::Gzip),
+            Encoder::Deflate(..) => Some(Encoding::Deflate),
+        }
+    }
+
+    /// Finishes compressing, and flushes all writing of a response to the client.
+    pub fn end(self) -> io::Result<()> {
+        let res = match self.encoder {
+            Encoder::Identity(res) => res,
+            Encoder::Gzip(encoder) => try!(encoder.finish()),
+            Encoder::Deflate(encoder) => try!(encoder.finish()),
+        };
+        res.end()
+    }
+}
+
+impl<'a> Write for CompressedResponse<'a> {
+    #[inline]
+    fn write(&mut self, msg: &[u8]) -> io::Result<usize> {
+        match self.encoder {
+            Encoder::Identity(ref mut res) => res.write(msg),
+            Encoder::Gzip(ref mut encoder) => encoder.write(msg),
+            Encoder::Deflate(ref mut encoder) => encoder.write(msg),
+        }
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        match self.encoder {
+            Encoder::Identity(ref mut res) => res.flush(),
+            Encoder::Gzip(ref mut encoder) => encoder.flush(),
+            Encoder::Deflate(ref mut encoder) => encoder.flush(),
+        }
+    }
+}
+
+impl<'a> fmt::Debug for CompressedResponse<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CompressedResponse({:?})", self.encoding())
+    }
+}
+
 #[derive(PartialEq)]
 enum Body {
     Chunked,
@@ -267,7 +469,12 @@ impl<'a, T: Any> Drop for Response<'a, T> {
 
 #[cfg(test)]
 mod tests {
-    use header::Headers;
+    use std::io::Read;
+
+    use flate2::read::{GzDecoder, ZlibDecoder};
+
+    use header::{AcceptEncoding, ContentEncoding, ContentLength, ContentType, Encoding, Headers};
+    use header::{Vary, qitem, Quality, QualityItem};
     use mock::MockStream;
     use super::Response;
 
@@ -386,4 +593,153 @@ mod tests {
             ""
         }
     }
+
+    fn accept(encodings: Vec<QualityItem<Encoding>>) -> AcceptEncoding {
+        AcceptEncoding(encodings)
+    }
+
+    /// The body of a chunked response written to the stream.
+    fn chunked_body(stream: MockStream) -> Vec<u8> {
+        let written = stream.write;
+        let mut pos = written.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        let mut body = vec![];
+        loop {
+            let line_end = pos + written[pos..].windows(2).position(|w| w == b"\r\n").unwrap();
+            let size = ::std::str::from_utf8(&written[pos..line_end]).unwrap();
+            let size = usize::from_str_radix(size, 16).unwrap();
+            if size == 0 {
+                return body;
+            }
+            body.extend(&written[line_end + 2..line_end + 2 + size]);
+            pos = line_end + 2 + size + 2;
+        }
+    }
+
+    #[test]
+    fn test_send_compressed_gzip() {
+        let body = vec![b'a'; 2048];
+        let mut headers = Headers::new();
+        let mut stream = MockStream::new();
+        {
+            let res = Response::new(&mut stream, &mut headers);
+            let accept = accept(vec![qitem(Encoding::Deflate), qitem(Encoding::Gzip)]);
+            res.send_compressed(Some(&accept), &body).unwrap();
+        }
+
+        assert_eq!(headers.get(), Some(&ContentEncoding(vec![Encoding::Gzip])));
+        assert_eq!(headers.get(), Some(&Vary::Items(vec!["Accept-Encoding".parse().unwrap()])));
+        assert!(headers.get::<ContentLength>().is_none());
+        let compressed = chunked_body(stream);
+        assert!(compressed.len() < body.len());
+        let mut decoded = vec![];
+        GzDecoder::new(&compressed[..]).unwrap().read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, body);
+    }
+
+    #[test]
+    fn test_start_compressed_deflate_quality() {
+        let mut headers = Headers::new();
+        let mut stream = MockStream::new();
+        {
+            use std::io::Write;
+            let res = Response::new(&mut stream, &mut headers);
+            let accept = accept(vec![
+                QualityItem::new(Encoding::Gzip, Quality(500)),
+                qitem(Encoding::Deflate),
+            ]);
+            let mut res = res.start_compressed(Some(&accept)).unwrap();
+            assert_eq!(res.encoding(), Some(Encoding::Deflate));
+            res.write_all(b"streamed").unwrap();
+            res.end().unwrap();
+        }
+
+        assert_eq!(headers.get(), Some(&ContentEncoding(vec![Encoding::Deflate])));
+        let mut decoded = String::new();
+        ZlibDecoder::new(&chunked_body(stream)[..]).read_to_string(&mut decoded).unwrap();
+        assert_eq!(decoded, "streamed");
+    }
+
+    #[test]
+    fn test_compressed_not_accepted() {
+        let mut headers = Headers::new();
+        let mut stream = MockStream::new();
+        {
+            let res = Response::new(&mut stream, &mut headers);
+            let accept = accept(vec![
+                qitem(Encoding::Identity),
+                QualityItem::new(Encoding::Gzip, Quality(0)),
+            ]);
+            res.send_compressed(Some(&accept), &[b'a'; 2048]).unwrap();
+        }
+        assert!(headers.get::<ContentEncoding>().is_none());
+        assert_eq!(headers.get(), Some(&ContentLength(2048)));
+        assert!(headers.has::<Vary>());
+
+        let mut headers = Headers::new();
+        let mut stream = MockStream::new();
+        {
+            let res = Response::new(&mut stream, &mut headers);
+            res.send_compressed(None, &[b'a'; 2048]).unwrap();
+        }
+        assert!(headers.get::<ContentEncoding>().is_none());
+    }
+
+    #[test]
+    fn test_compressed_star() {
+        let mut headers = Headers::new();
+        let mut stream = MockStream::new();
+        {
+            let res = Response::new(&mut stream, &mut headers);
+            let accept = accept(vec![qitem(Encoding::EncodingExt("*".to_owned()))]);
+            res.send_compressed(Some(&accept), &[b'a'; 2048]).unwrap();
+        }
+        assert_eq!(headers.get(), Some(&ContentEncoding(vec![Encoding::Gzip])));
+    }
+
+    #[test]
+    fn test_compressed_skips_small_and_compressed_types() {
+        let accept = accept(vec![qitem(Encoding::Gzip)]);
+
+        let mut headers = Headers::new();
+        let mut stream = MockStream::new();
+        {
+            let res = Response::new(&mut stream, &mut headers);
+            res.send_compressed(Some(&accept), b"tiny").unwrap();
+        }
+        assert!(headers.get::<ContentEncoding>().is_none());
+        assert!(!headers.has::<Vary>());
+
+        let mut headers = Headers::new();
+        let mut stream = MockStream::new();
+        {
+            let mut res = Response::new(&mut stream, &mut headers);
+            res.headers_mut().set(ContentType("image/png".parse().unwrap()));
+            res.send_compressed(Some(&accept), &[0; 2048]).unwrap();
+        }
+        assert!(headers.get::<ContentEncoding>().is_none());
+
+        let mut headers = Headers::new();
+        let mut stream = MockStream::new();
+        {
+            let mut res = Response::new(&mut stream, &mut headers);
+            res.headers_mut().set(ContentType("image/svg+xml".parse().unwrap()));
+            res.send_compressed(Some(&accept), &[b'a'; 2048]).unwrap();
+        }
+        assert_eq!(headers.get(), Some(&ContentEncoding(vec![Encoding::Gzip])));
+    }
+
+    #[test]
+    fn test_compressed_keeps_vary() {
+        let mut headers = Headers::new();
+        headers.set(Vary::Items(vec!["Cookie".parse().unwrap()]));
+        let mut stream = MockStream::new();
+        {
+            let res = Response::new(&mut stream, &mut headers);
+            res.start_compressed(None).unwrap().end().unwrap();
+        }
+        assert_eq!(headers.get(), Some(&Vary::Items(vec![
+            "Cookie".parse().unwrap(),
+            "Accept-Encoding".parse().unwrap(),
+        ])));
+    }
 }
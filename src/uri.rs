@@ -1,7 +1,7 @@
 //! HTTP RequestUris
 use std::fmt::{Display, self};
 use std::str::FromStr;
-use url::Url;
+use url::{Url, form_urlencoded};
 use url::ParseError as UrlError;
 
 use Error;
@@ -50,6 +50,28 @@ pub enum RequestUri {
     Star,
 }
 
+impl RequestUri {
+    /// The query string of this target, if it has one, as it was sent.
+    pub fn query(&self) -> Option<&str> {
+        match *self {
+            RequestUri::AbsolutePath(ref path) => path.find('?').map(|i| &path[i + 1..]),
+            RequestUri::AbsoluteUri(ref url) => url.query.as_ref().map(|query| &query[..]),
+            _ => None
+        }
+    }
+
+    /// The pairs of the query string, percent-decoded as
+    /// `application/x-www-form-urlencoded`.
+    ///
+    /// Without a query string, there are no pairs.
+    pub fn query_pairs(&self) -> Vec<(String, String)> {
+        match self.query() {
+            Some(query) => form_urlencoded::parse(query.as_bytes()),
+            None => vec![]
+        }
+    }
+}
+
 impl FromStr for RequestUri {
     type Err = Error;
 
@@ -108,3 +130,26 @@ fn test_uri_display() {
     assert_display("/", RequestUri::AbsolutePath("/".to_owned()));
 
 }
+
+#[test]
+fn test_uri_query() {
+    fn query(s: &str) -> Option<String> {
+        s.parse::<RequestUri>().unwrap().query().map(|q| q.to_owned())
+    }
+
+    assert_eq!(query("/"), None);
+    assert_eq!(query("/where?q=now"), Some("q=now".to_owned()));
+    assert_eq!(query("http://hyper.rs/?a=b"), Some("a=b".to_owned()));
+    assert_eq!(query("*"), None);
+}
+
+#[test]
+fn test_uri_query_pairs() {
+    let uri: RequestUri = "/search?q=caf%C3%A9+au+lait&page=2&empty".parse().unwrap();
+    assert_eq!(uri.query_pairs(), vec![
+        ("q".to_owned(), "café au lait".to_owned()),
+        ("page".to_owned(), "2".to_owned()),
+        ("empty".to_owned(), "".to_owned()),
+    ]);
+    assert_eq!(RequestUri::Star.query_pairs(), vec![]);
+}